@@ -0,0 +1,122 @@
+use crate::{cache, convert, probe, shell, storage};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+const STORE_FILE_NAME: &str = "loops.json";
+
+/// Loop points of a file in seconds. The loop plays once both are set.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct AbLoop {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbLoopUpdate {
+    pub filePath: String,
+    #[serde(flatten)]
+    pub abLoop: AbLoop,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbLoopExport {
+    pub filePath: String,
+    pub file: String,
+}
+
+/// Loop points by file key
+#[derive(Default)]
+pub struct AbLoops {
+    store: BTreeMap<String, AbLoop>,
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(STORE_FILE_NAME))
+}
+
+pub fn setup(app: &tauri::App) {
+    let store = store_path(app.handle()).ok().and_then(|path| storage::read_json(&path)).unwrap_or_default();
+    app.manage(Mutex::new(AbLoops {
+        store,
+    }));
+}
+
+pub fn get(app: &AppHandle, file_path: &str) -> Result<AbLoop, String> {
+    let key = cache::file_key(file_path)?;
+    Ok(app.state::<Mutex<AbLoops>>().lock().unwrap().store.get(&key).copied().unwrap_or_default())
+}
+
+/// Replaces the loop points of the file. Clearing both forgets the file.
+pub fn set(app: &AppHandle, update: AbLoopUpdate) -> Result<AbLoop, String> {
+    let ab_loop = update.abLoop;
+
+    for time in [ab_loop.start, ab_loop.end].into_iter().flatten() {
+        if !time.is_finite() || time < 0.0 {
+            return Err(format!("Invalid loop point: {}", time));
+        }
+    }
+
+    if let (Some(start), Some(end)) = (ab_loop.start, ab_loop.end) {
+        if start >= end {
+            return Err(String::from("The loop must end after it starts"));
+        }
+    }
+
+    let key = cache::file_key(&update.filePath)?;
+    let state = app.state::<Mutex<AbLoops>>();
+    let mut loops = state.lock().unwrap();
+
+    if ab_loop == AbLoop::default() {
+        loops.store.remove(&key);
+    } else {
+        loops.store.insert(key, ab_loop);
+    }
+
+    storage::write_json(&store_path(app)?, &loops.store)?;
+
+    Ok(ab_loop)
+}
+
+/// Moves the loop points to the key the file has after a rename or an edit
+pub fn rekey(app: &AppHandle, old_key: &str, new_key: &str) -> Result<(), String> {
+    let state = app.state::<Mutex<AbLoops>>();
+    let mut loops = state.lock().unwrap();
+
+    let Some(ab_loop) = loops.store.remove(old_key) else {
+        return Ok(());
+    };
+    loops.store.insert(new_key.to_string(), ab_loop);
+
+    storage::write_json(&store_path(app)?, &loops.store)
+}
+
+/// Encodes the part of the file between the loop points into a new clip
+pub async fn export(app: &AppHandle, export: AbLoopExport) -> Result<(), String> {
+    let AbLoop {
+        start: Some(start),
+        end: Some(end),
+    } = get(app, &export.filePath)?
+    else {
+        return Err(String::from("Set both loop points first"));
+    };
+
+    if export.file == export.filePath {
+        return Err(String::from("The clip must be saved as a new file"));
+    }
+
+    let probe = probe::probe(app, &export.filePath).await?;
+    let streams = [convert::select(&probe, "video", None)?, convert::select(&probe, "audio", None)?];
+
+    // Seeking before the input is frame accurate as the streams are encoded again
+    let mut args: Vec<String> = ["-hide_banner", "-v", "error", "-ss", &start.to_string(), "-i", &export.filePath, "-t", &(end - start).to_string()].iter().map(|arg| arg.to_string()).collect();
+    for stream in streams.into_iter().flatten() {
+        args.extend(["-map".to_string(), format!("0:{}", stream.index)]);
+    }
+    args.extend(["-sn", "-map_chapters", "-1", "-y", &export.file].iter().map(|arg| arg.to_string()));
+
+    shell::exec(app, "ffmpeg", args).await.map(|_| ())
+}
@@ -0,0 +1,380 @@
+use crate::{
+    cache,
+    menu::{self, PlayerMenu, PLAYER},
+    settings, storage,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.json";
+const BOOKMARKS_CHANGED_EVENT: &str = "bookmarks-changed";
+const DEFAULT_COLOR: &str = "#f5c211";
+
+static ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Bookmark {
+    pub id: String,
+    pub time: f64,
+    pub label: String,
+    pub color: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct BookmarkList {
+    /// Last known location of the file, kept so that the store stays readable
+    file_path: String,
+    bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Default)]
+pub struct Bookmarks {
+    files: BTreeMap<String, BookmarkList>,
+    current: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewBookmark {
+    pub filePath: String,
+    pub time: f64,
+    pub label: String,
+    pub color: Option<String>,
+    pub note: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookmarkUpdate {
+    pub filePath: String,
+    pub bookmark: Bookmark,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookmarkRemoval {
+    pub filePath: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum BookmarkFormat {
+    Json,
+    Chapters,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookmarkTransfer {
+    pub filePath: String,
+    pub file: String,
+    pub format: BookmarkFormat,
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(BOOKMARKS_FILE_NAME))
+}
+
+pub fn setup(app: &tauri::App) {
+    let files = store_path(app.handle()).ok().and_then(|path| storage::read_json::<BTreeMap<String, BookmarkList>>(&path)).unwrap_or_default();
+    app.manage(Mutex::new(Bookmarks {
+        files,
+        current: None,
+    }));
+}
+
+fn new_id() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis()).unwrap_or_default();
+    format!("{:x}{:x}", millis, ID_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+fn validate(bookmark: &Bookmark) -> Result<(), String> {
+    if !bookmark.time.is_finite() || bookmark.time < 0.0 {
+        return Err(format!("Invalid bookmark time: {}", bookmark.time));
+    }
+
+    let is_hex_color = bookmark.color.len() == 7 && bookmark.color.starts_with('#') && bookmark.color[1..].chars().all(|char| char.is_ascii_hexdigit());
+    if !is_hex_color {
+        return Err(format!("Invalid bookmark color: {}", bookmark.color));
+    }
+
+    Ok(())
+}
+
+/// Applies a change to the bookmarks of a file, then persists the store and refreshes the menu and the Player window when the file is the one playing
+fn modify<T, F>(app: &AppHandle, file_path: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut Vec<Bookmark>) -> Result<T, String>,
+{
+    let key = cache::file_key(file_path)?;
+    let state = app.state::<Mutex<Bookmarks>>();
+    let mut store = state.lock().unwrap();
+
+    let list = store.files.entry(key.clone()).or_default();
+    list.file_path = file_path.to_string();
+    let result = f(&mut list.bookmarks);
+    list.bookmarks.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    if list.bookmarks.is_empty() {
+        store.files.remove(&key);
+    }
+
+    let result = result?;
+
+    storage::write_json(&store_path(app)?, &store.files)?;
+
+    let is_current = store.current.as_deref() == Some(key.as_str());
+    drop(store);
+
+    if is_current {
+        refresh_menu(app)?;
+        app.emit(BOOKMARKS_CHANGED_EVENT, bookmarks_of(app, &key)).map_err(|e| e.to_string())?;
+    }
+
+    Ok(result)
+}
+
+fn bookmarks_of(app: &AppHandle, key: &str) -> Vec<Bookmark> {
+    let state = app.state::<Mutex<Bookmarks>>();
+    let store = state.lock().unwrap();
+    store.files.get(key).map(|list| list.bookmarks.clone()).unwrap_or_default()
+}
+
+/// Makes the file the one listed in the Bookmarks submenu and returns its bookmarks
+pub fn load(app: &AppHandle, file_path: &str) -> Result<Vec<Bookmark>, String> {
+    let key = cache::file_key(file_path)?;
+    app.state::<Mutex<Bookmarks>>().lock().unwrap().current = Some(key.clone());
+    refresh_menu(app)?;
+    Ok(bookmarks_of(app, &key))
+}
+
+pub fn get(app: &AppHandle, file_path: &str) -> Result<Vec<Bookmark>, String> {
+    Ok(bookmarks_of(app, &cache::file_key(file_path)?))
+}
+
+fn create(time: f64, label: String, color: Option<String>, note: Option<String>) -> Result<Bookmark, String> {
+    let bookmark = Bookmark {
+        id: new_id(),
+        time,
+        label,
+        color: color.unwrap_or_else(|| DEFAULT_COLOR.to_string()),
+        note,
+    };
+    validate(&bookmark)?;
+    Ok(bookmark)
+}
+
+pub fn add(app: &AppHandle, new_bookmark: NewBookmark) -> Result<Bookmark, String> {
+    let bookmark = create(new_bookmark.time, new_bookmark.label, new_bookmark.color, new_bookmark.note)?;
+
+    modify(app, &new_bookmark.filePath, |bookmarks| {
+        bookmarks.push(bookmark.clone());
+        Ok(bookmark)
+    })
+}
+
+/// Adds a bookmark for every time and label, saving the store and refreshing the menu once for all of them
+pub fn add_all(app: &AppHandle, file_path: &str, entries: Vec<(f64, String)>) -> Result<(), String> {
+    let added = entries.into_iter().map(|(time, label)| create(time, label, None, None)).collect::<Result<Vec<_>, _>>()?;
+
+    modify(app, file_path, |bookmarks| {
+        bookmarks.extend(added);
+        Ok(())
+    })
+}
+
+pub fn update(app: &AppHandle, update: BookmarkUpdate) -> Result<Bookmark, String> {
+    validate(&update.bookmark)?;
+
+    modify(app, &update.filePath, |bookmarks| {
+        let bookmark = bookmarks.iter_mut().find(|bookmark| bookmark.id == update.bookmark.id).ok_or_else(|| format!("Bookmark not found: {}", update.bookmark.id))?;
+        *bookmark = update.bookmark;
+        Ok(bookmark.clone())
+    })
+}
+
+pub fn remove(app: &AppHandle, removal: BookmarkRemoval) -> Result<(), String> {
+    modify(app, &removal.filePath, |bookmarks| {
+        bookmarks.retain(|bookmark| bookmark.id != removal.id);
+        Ok(())
+    })
+}
+
+/// Moves the bookmarks to the key the file has after a rename or an edit
+pub fn rekey(app: &AppHandle, old_key: &str, new_key: &str, file_path: &str) -> Result<(), String> {
+    let state = app.state::<Mutex<Bookmarks>>();
+    let mut store = state.lock().unwrap();
+
+    let Some(mut list) = store.files.remove(old_key) else {
+        return Ok(());
+    };
+    list.file_path = file_path.to_string();
+    store.files.insert(new_key.to_string(), list);
+
+    if store.current.as_deref() == Some(old_key) {
+        store.current = Some(new_key.to_string());
+    }
+
+    storage::write_json(&store_path(app)?, &store.files)
+}
+
+pub fn export(app: &AppHandle, transfer: BookmarkTransfer) -> Result<(), String> {
+    let bookmarks = get(app, &transfer.filePath)?;
+
+    let content = match transfer.format {
+        BookmarkFormat::Json => serde_json::to_string_pretty(&bookmarks).map_err(|e| e.to_string())?,
+        BookmarkFormat::Chapters => to_chapters(&bookmarks),
+    };
+
+    storage::write_atomic(Path::new(&transfer.file), content.as_bytes())
+}
+
+/// Adds the bookmarks found in the file to the existing ones and returns the new list
+pub fn import(app: &AppHandle, transfer: BookmarkTransfer) -> Result<Vec<Bookmark>, String> {
+    let content = std::fs::read_to_string(&transfer.file).map_err(|e| e.to_string())?;
+
+    let imported = match transfer.format {
+        BookmarkFormat::Json => serde_json::from_str::<Vec<Bookmark>>(&content).map_err(|e| e.to_string())?,
+        BookmarkFormat::Chapters => from_chapters(&content)?,
+    };
+
+    for bookmark in &imported {
+        validate(bookmark)?;
+    }
+
+    modify(app, &transfer.filePath, |bookmarks| {
+        for mut bookmark in imported {
+            bookmark.id = new_id();
+            bookmarks.push(bookmark);
+        }
+        Ok(())
+    })?;
+
+    get(app, &transfer.filePath)
+}
+
+fn refresh_menu(app: &AppHandle) -> Result<(), String> {
+    let current = app.state::<Mutex<Bookmarks>>().lock().unwrap().current.clone();
+    let bookmarks = current.map(|key| bookmarks_of(app, &key)).unwrap_or_default();
+
+    let entries = bookmarks.iter().map(|bookmark| (bookmark.id.clone(), format!("{}  {}", menu::format_time(bookmark.time), bookmark.label))).collect();
+
+    menu::set_entries(app, PLAYER, PlayerMenu::Bookmarks, entries, None, &settings::get(app));
+    Ok(())
+}
+
+pub fn format_timestamp(time: f64) -> String {
+    let millis = (time * 1000.0).round() as u64;
+    let (hours, minutes, seconds, millis) = (millis / 3_600_000, millis % 3_600_000 / 60_000, millis % 60_000 / 1000, millis % 1000);
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+/// Parses "HH:MM:SS.mmm", "MM:SS" or plain seconds
+pub fn parse_timestamp(value: &str) -> Option<f64> {
+    value.trim().split(':').try_fold(0.0, |total, part| part.trim().parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0).map(|value| total * 60.0 + value))
+}
+
+/// Writes the simple OGM chapter format ("CHAPTER01=00:00:00.000" / "CHAPTER01NAME=...") read by most players and muxers
+fn to_chapters(bookmarks: &[Bookmark]) -> String {
+    bookmarks.iter().enumerate().map(|(i, bookmark)| format!("CHAPTER{:02}={}\nCHAPTER{:02}NAME={}\n", i + 1, format_timestamp(bookmark.time), i + 1, bookmark.label)).collect()
+}
+
+fn from_chapters(content: &str) -> Result<Vec<Bookmark>, String> {
+    let mut chapters: BTreeMap<u32, (Option<f64>, String)> = BTreeMap::new();
+
+    for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("Invalid chapter line: {}", line));
+        };
+
+        let Some(number) = key.strip_prefix("CHAPTER") else {
+            return Err(format!("Invalid chapter line: {}", line));
+        };
+
+        if let Some(number) = number.strip_suffix("NAME") {
+            let number = number.parse::<u32>().map_err(|_| format!("Invalid chapter line: {}", line))?;
+            chapters.entry(number).or_default().1 = value.to_string();
+        } else {
+            let number = number.parse::<u32>().map_err(|_| format!("Invalid chapter line: {}", line))?;
+            let time = parse_timestamp(value).ok_or_else(|| format!("Invalid chapter time: {}", value))?;
+            chapters.entry(number).or_default().0 = Some(time);
+        }
+    }
+
+    chapters
+        .into_iter()
+        .map(|(number, (time, label))| {
+            let time = time.ok_or_else(|| format!("Chapter {} has no time", number))?;
+            Ok(Bookmark {
+                id: String::new(),
+                time,
+                label,
+                color: DEFAULT_COLOR.to_string(),
+                note: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(time: f64, label: &str) -> Bookmark {
+        Bookmark {
+            id: String::new(),
+            time,
+            label: label.to_string(),
+            color: DEFAULT_COLOR.to_string(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03.500"), Some(3723.5));
+        assert_eq!(parse_timestamp("02:03"), Some(123.0));
+        assert_eq!(parse_timestamp(" 42.25 "), Some(42.25));
+        assert_eq!(parse_timestamp("1:-2"), None);
+        assert_eq!(parse_timestamp("1:x"), None);
+        assert_eq!(parse_timestamp(""), None);
+
+        for time in [0.0, 0.001, 59.999, 3723.5, 86399.0] {
+            assert_eq!(parse_timestamp(&format_timestamp(time)), Some(time));
+        }
+    }
+
+    #[test]
+    fn chapters_round_trip() {
+        let bookmarks = vec![bookmark(0.0, "Opening"), bookmark(95.25, "Part = two"), bookmark(3723.5, "")];
+
+        let content = to_chapters(&bookmarks);
+        assert!(content.starts_with("CHAPTER01=00:00:00.000\nCHAPTER01NAME=Opening\n"));
+        assert_eq!(from_chapters(&content).unwrap(), bookmarks);
+    }
+
+    #[test]
+    fn reads_chapters_in_number_order() {
+        let content = "CHAPTER02NAME=Second\r\nCHAPTER02=00:01:00.000\r\n\r\nCHAPTER01=00:00:10.000\r\n";
+        assert_eq!(from_chapters(content).unwrap(), vec![bookmark(10.0, ""), bookmark(60.0, "Second")]);
+    }
+
+    #[test]
+    fn rejects_invalid_chapters() {
+        assert!(from_chapters("CHAPTER01NAME=No time").is_err());
+        assert!(from_chapters("CHAPTER01=later").is_err());
+        assert!(from_chapters("TITLE=Movie").is_err());
+        assert!(from_chapters("CHAPTERXX=00:00:00.000").is_err());
+    }
+}
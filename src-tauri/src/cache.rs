@@ -0,0 +1,160 @@
+use crate::{ab_loop, bookmarks, journal::MovedFile, storage, subtitles};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+/// Size a category may grow to before its least recently used entries are removed
+const MAX_CATEGORY_BYTES: u64 = 256 * 1024 * 1024;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Identifies a media file by its name, size and modification time, so data stored for it
+/// survives a move to another directory but not an edit of its contents
+pub fn file_key<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    let path = path.as_ref();
+    let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    let modified = metadata.modified().map_err(|e| e.to_string())?.duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos()).unwrap_or_default();
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, name.as_bytes());
+    hash = fnv1a(hash, &metadata.len().to_le_bytes());
+    hash = fnv1a(hash, &modified.to_le_bytes());
+
+    Ok(format!("{:016x}", hash))
+}
+
+/// Moves what is remembered for a file, its bookmarks, subtitle choice and offset and loop points, from the key
+/// it had before a rename or an edit to the key it has now. A store that cannot be saved keeps the move in memory
+/// and writes it with its next change, so the rename or edit itself is not reported as failed.
+pub fn rekey(app: &AppHandle, old_key: &str, file_path: &str) {
+    let Ok(new_key) = file_key(file_path) else {
+        return;
+    };
+    if new_key == old_key {
+        return;
+    }
+
+    let _ = bookmarks::rekey(app, old_key, &new_key, file_path);
+    let _ = subtitles::rekey(app, old_key, &new_key);
+    let _ = ab_loop::rekey(app, old_key, &new_key);
+}
+
+/// Keys of the files by path, taken before the files are moved or renamed
+pub fn file_keys<'a, I: IntoIterator<Item = &'a str>>(paths: I) -> HashMap<String, String> {
+    paths.into_iter().filter_map(|path| Some((path.to_string(), file_key(path).ok()?))).collect()
+}
+
+/// Rekeys every moved file whose key was taken before the move
+pub fn rekey_moved(app: &AppHandle, keys: &HashMap<String, String>, files: &[MovedFile]) {
+    for file in files {
+        if let Some(old_key) = keys.get(&file.from) {
+            rekey(app, old_key, &file.to);
+        }
+    }
+}
+
+/// Location of the cached `category` data for a media file
+pub fn cache_path(app: &AppHandle, category: &str, file_path: &str, extension: &str) -> Result<PathBuf, String> {
+    let cache_dir = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+    Ok(cache_dir.join(category).join(format!("{}.{}", file_key(file_path)?, extension)))
+}
+
+pub fn read(app: &AppHandle, category: &str, file_path: &str, extension: &str) -> Option<Vec<u8>> {
+    let path = cache_path(app, category, file_path, extension).ok()?;
+    let data = std::fs::read(&path).ok()?;
+    touch(&path);
+    Some(data)
+}
+
+pub fn write(app: &AppHandle, category: &str, file_path: &str, extension: &str, data: &[u8]) -> Result<(), String> {
+    storage::write_atomic(&cache_path(app, category, file_path, extension)?, data)?;
+    prune(app, category);
+    Ok(())
+}
+
+/// Marks a cached entry as used. The modification time orders the entries for eviction.
+pub fn touch(path: &Path) {
+    if let Ok(file) = File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Removes the least recently used entries of the category once it outgrows its limit.
+/// Called after writing, so a failure only leaves the cache larger than it should be.
+pub fn prune(app: &AppHandle, category: &str) {
+    if let Ok(cache_dir) = app.path().app_cache_dir() {
+        let _ = prune_dir(&cache_dir.join(category), MAX_CATEGORY_BYTES);
+    }
+}
+
+fn prune_dir(dir: &Path, max_bytes: u64) -> std::io::Result<()> {
+    let mut entries: Vec<(SystemTime, u64, PathBuf)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    if total <= max_bytes {
+        return Ok(());
+    }
+
+    entries.sort_by_key(|(modified, _, _)| *modified);
+    // The newest entry is the one just written and stays even when it alone is over the limit
+    entries.pop();
+
+    for (_, len, path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn prunes_least_recently_used_entries() {
+        let dir = std::env::temp_dir().join(format!("cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let now = SystemTime::now();
+        for (name, age) in [("old", 30), ("used", 10), ("middle", 20), ("new", 0)] {
+            let path = dir.join(name);
+            std::fs::write(&path, [0; 100]).unwrap();
+            File::options().write(true).open(&path).unwrap().set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        prune_dir(&dir, 400).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+
+        prune_dir(&dir, 250).unwrap();
+        let mut left: Vec<String> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+        left.sort();
+        assert_eq!(left, ["new", "used"]);
+
+        // The newest entry is kept even when it alone is over the limit
+        prune_dir(&dir, 50).unwrap();
+        assert!(dir.join("new").exists());
+        assert!(!dir.join("used").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    menu::{self, PlayerMenu, PLAYER},
+    probe::{self, Chapter},
+    settings,
+};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// The file whose chapters the Chapters submenu should list
+#[derive(Default)]
+pub struct Chapters {
+    current: String,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Mutex::new(Chapters::default()));
+}
+
+/// Returns the chapters of the file and lists them in the Chapters submenu, unless another file was loaded in the meantime
+pub async fn load(app: &AppHandle, file_path: &str) -> Result<Vec<Chapter>, String> {
+    app.state::<Mutex<Chapters>>().lock().unwrap().current = file_path.to_string();
+
+    let chapters = probe::probe(app, file_path).await.map(|probe| probe.chapters);
+    let entries = chapters
+        .as_ref()
+        .map(|chapters| chapters.iter().enumerate().map(|(i, chapter)| (chapter.start.to_string(), format!("{}  {}", menu::format_time(chapter.start), title(chapter, i)))).collect())
+        .unwrap_or_default();
+
+    if app.state::<Mutex<Chapters>>().lock().unwrap().current == file_path {
+        let app_handle = app.clone();
+        app.run_on_main_thread(move || {
+            menu::set_entries(&app_handle, PLAYER, PlayerMenu::Chapters, entries, None, &settings::get(&app_handle));
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    chapters
+}
+
+fn title(chapter: &Chapter, index: usize) -> String {
+    if chapter.title.is_empty() {
+        format!("#{}", index + 1)
+    } else {
+        chapter.title.clone()
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    probe::{self, Probe, Stream},
+    shell, silence,
+};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const BITMAP_SUBTITLE_CODECS: [&str; 4] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Picks a stream of one type. Omitted selectors fall back to the stream ffmpeg would choose.
+#[derive(Debug, Clone, Deserialize)]
+pub enum StreamSelector {
+    /// Absolute stream index as listed by ffprobe
+    Index(usize),
+    /// ISO 639 language tag, such as "eng" or "jpn"
+    Language(String),
+    /// Leaves the stream type out of the output
+    Exclude,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamSelection {
+    pub video: Option<StreamSelector>,
+    pub audio: Option<StreamSelector>,
+    pub subtitle: Option<StreamSelector>,
+    /// Renders the selected subtitle into the picture instead of muxing it as a track
+    pub burnSubtitle: bool,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConvertStreamsRequest {
+    pub filePath: String,
+    pub convertType: String,
+    pub format: String,
+    pub selection: StreamSelection,
+    /// Cuts the silent parts of the selected audio stream out of the output
+    #[serde(default)]
+    pub removeSilence: bool,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ConvertStreams {
+    pub video: Option<usize>,
+    pub audio: Option<usize>,
+    pub subtitle: Option<usize>,
+    /// "-map" and subtitle codec arguments to place after the input
+    pub args: Vec<String>,
+    /// Appended to the video filter chain when the subtitle is burned in or silence is removed
+    pub videoFilter: Option<String>,
+    /// Placed before the audio filter chain when silence is removed
+    pub audioFilter: Option<String>,
+}
+
+/// Resolves the requested streams against the probed stream list and returns the matching ffmpeg arguments
+pub async fn resolve(app: &AppHandle, request: ConvertStreamsRequest) -> Result<ConvertStreams, String> {
+    let probe = probe::probe(app, &request.filePath).await?;
+    let mut streams = resolve_streams(&probe, &request)?;
+
+    if request.removeSilence {
+        remove_silence(app, &probe, &request, &mut streams).await?;
+    }
+
+    Ok(streams)
+}
+
+async fn remove_silence(app: &AppHandle, probe: &Probe, request: &ConvertStreamsRequest, streams: &mut ConvertStreams) -> Result<(), String> {
+    let audio = streams.audio.and_then(|index| probe.streams.iter().find(|stream| stream.index == index)).ok_or_else(|| String::from("Removing silence requires an audio stream"))?;
+
+    // A muxed subtitle track would keep the original timing
+    if streams.subtitle.is_some() && !request.selection.burnSubtitle {
+        return Err(String::from("Subtitle tracks cannot be kept when removing silence. Burn them in instead."));
+    }
+
+    let intervals = silence::detect(app, &request.filePath, audio, shell::new_cancellation_token("ffmpeg")).await?;
+    if intervals.is_empty() {
+        return Ok(());
+    }
+
+    let expression = silence::drop_expression(&intervals);
+    streams.audioFilter = Some(format!("aselect={},asetpts=N/SR/TB", expression));
+
+    if streams.video.is_some() {
+        let select = format!("select={},setpts=N/FRAME_RATE/TB", expression);
+        streams.videoFilter = Some(match streams.videoFilter.take() {
+            Some(filter) => format!("{},{}", filter, select),
+            None => select,
+        });
+    }
+
+    Ok(())
+}
+
+fn resolve_streams(probe: &Probe, request: &ConvertStreamsRequest) -> Result<ConvertStreams, String> {
+    let selection = &request.selection;
+    let is_video = request.convertType == "Video";
+
+    let video = if is_video {
+        let video = select(probe, "video", selection.video.as_ref())?;
+        if video.is_none() {
+            return Err(String::from("Video conversion requires a video stream"));
+        }
+        video
+    } else {
+        None
+    };
+
+    let audio = select(probe, "audio", selection.audio.as_ref())?;
+    if !is_video && audio.is_none() {
+        return Err(String::from("Audio conversion requires an audio stream"));
+    }
+
+    // Subtitles are only carried over when asked for, as most source files have tracks the target cannot hold
+    let subtitle = match (is_video, &selection.subtitle) {
+        (true, Some(selector)) => select(probe, "subtitle", Some(selector))?,
+        _ => None,
+    };
+
+    let mut streams = ConvertStreams {
+        video: video.map(|stream| stream.index),
+        audio: audio.map(|stream| stream.index),
+        subtitle: subtitle.map(|stream| stream.index),
+        ..Default::default()
+    };
+
+    for stream in [video, audio].into_iter().flatten() {
+        streams.args.extend(["-map".to_string(), format!("0:{}", stream.index)]);
+    }
+
+    if let Some(subtitle) = subtitle {
+        let is_bitmap = BITMAP_SUBTITLE_CODECS.contains(&subtitle.codec_name.as_str());
+
+        if selection.burnSubtitle {
+            if is_bitmap {
+                return Err(format!("Picture based subtitles ({}) cannot be burned in", subtitle.codec_name));
+            }
+            // The subtitles filter counts subtitle streams only
+            let position = probe.streams.iter().filter(|stream| stream.codec_type == "subtitle").position(|stream| stream.index == subtitle.index).unwrap_or_default();
+            streams.videoFilter = Some(format!("subtitles={}:si={}", escape_filter_path(&request.filePath), position));
+        } else {
+            let codec = subtitle_codec(&request.format, is_bitmap)?;
+            streams.args.extend(["-map".to_string(), format!("0:{}", subtitle.index), "-c:s".to_string(), codec.to_string()]);
+        }
+    }
+
+    if streams.subtitle.is_none() {
+        streams.args.push("-sn".to_string());
+    }
+
+    Ok(streams)
+}
+
+pub fn select<'a>(probe: &'a Probe, codec_type: &str, selector: Option<&StreamSelector>) -> Result<Option<&'a Stream>, String> {
+    let mut candidates = probe.streams.iter().filter(|stream| stream.codec_type == codec_type).filter(|stream| codec_type != "video" || !stream.is_cover());
+
+    match selector {
+        None => {
+            let candidates: Vec<&Stream> = candidates.collect();
+            Ok(candidates.iter().find(|stream| stream.disposition.get("default").is_some_and(|default| *default == 1)).or(candidates.first()).copied())
+        }
+        Some(StreamSelector::Exclude) => Ok(None),
+        Some(StreamSelector::Index(index)) => match probe.streams.iter().find(|stream| stream.index == *index) {
+            Some(stream) if stream.codec_type == codec_type => Ok(Some(stream)),
+            Some(stream) => Err(format!("Stream #{} is {}, not {}", index, stream.codec_type, codec_type)),
+            None => Err(format!("Stream #{} does not exist", index)),
+        },
+        Some(StreamSelector::Language(language)) => candidates
+            .find(|stream| stream.tags.get("language").is_some_and(|tag| tag.eq_ignore_ascii_case(language)))
+            .map(Some)
+            .ok_or_else(|| format!("No {} stream in language \"{}\"", codec_type, language)),
+    }
+}
+
+fn subtitle_codec(format: &str, is_bitmap: bool) -> Result<&'static str, String> {
+    match format.to_lowercase().as_str() {
+        "mkv" => Ok("copy"),
+        "mp4" | "m4v" | "mov" | "3gp" if !is_bitmap => Ok("mov_text"),
+        "webm" if !is_bitmap => Ok("webvtt"),
+        _ => Err(format!("The {} format cannot hold this subtitle track. Burn it in instead.", format)),
+    }
+}
+
+/// Escapes a path for an option value inside a filtergraph, which is parsed twice
+fn escape_filter_path(file_path: &str) -> String {
+    let path = file_path.replace('\\', "/");
+    let escape = |value: &str, special: &[char]| {
+        value.chars().fold(String::new(), |mut escaped, char| {
+            if special.contains(&char) {
+                escaped.push('\\');
+            }
+            escaped.push(char);
+            escaped
+        })
+    };
+
+    escape(&escape(&path, &['\\', '\'', ':']), &['\\', '\'', '[', ']', ',', ';'])
+}
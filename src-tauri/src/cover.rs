@@ -0,0 +1,107 @@
+use crate::{cache, probe, shell};
+use std::path::Path;
+use tauri::AppHandle;
+
+const COVER_CACHE: &str = "cover";
+/// Sidecar image names in order of preference, matched without case or extension
+const SIDECAR_NAMES: [&str; 4] = ["cover", "folder", "front", "albumart"];
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Returns the path of an image for the file: its embedded cover, extracted once into the cache, or an image next to it
+pub async fn find(app: &AppHandle, file_path: &str) -> Result<Option<String>, String> {
+    if let Some(cover) = extract_embedded(app, file_path).await? {
+        return Ok(Some(cover));
+    }
+
+    Ok(find_sidecar(file_path))
+}
+
+async fn extract_embedded(app: &AppHandle, file_path: &str) -> Result<Option<String>, String> {
+    let probe = probe::probe(app, file_path).await?;
+    let Some(stream) = probe.streams.iter().find(|stream| stream.is_cover()) else {
+        return Ok(None);
+    };
+
+    let extension = if stream.codec_name == "png" {
+        "png"
+    } else {
+        "jpg"
+    };
+
+    let cover = cache::cache_path(app, COVER_CACHE, file_path, extension)?;
+    if cover.exists() {
+        cache::touch(&cover);
+        return Ok(Some(cover.to_string_lossy().to_string()));
+    }
+
+    if let Some(parent) = cover.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    // Written under a temporary name first so that an interrupted extraction is never mistaken for a cached cover
+    let temp = cover.with_extension(format!("tmp.{}", extension));
+    let codec = if matches!(stream.codec_name.as_str(), "mjpeg" | "png") {
+        "copy"
+    } else {
+        "mjpeg"
+    };
+    let args = ["-hide_banner", "-v", "error", "-i", file_path, "-map", &format!("0:{}", stream.index), "-frames:v", "1", "-c:v", codec, "-f", "image2", "-y", &temp.to_string_lossy()]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+    if let Err(e) = shell::exec(app, "ffmpeg", args).await {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    std::fs::rename(&temp, &cover).map_err(|e| e.to_string())?;
+    cache::prune(app, COVER_CACHE);
+
+    Ok(Some(cover.to_string_lossy().to_string()))
+}
+
+fn find_sidecar(file_path: &str) -> Option<String> {
+    let path = Path::new(file_path);
+    let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+
+    let images: Vec<(String, std::path::PathBuf)> = std::fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|image| image.extension().is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())))
+        .filter_map(|image| Some((image.file_stem()?.to_string_lossy().to_lowercase(), image)))
+        .collect();
+
+    // An image named after the file wins over the ones shared by the whole folder
+    let sidecar = std::iter::once(stem.as_str()).chain(SIDECAR_NAMES).find_map(|name| images.iter().find(|(image_stem, _)| image_stem == name));
+    sidecar.map(|(_, image)| image.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_sidecars_in_order_whatever_the_case() {
+        let dir = std::env::temp_dir().join(format!("cover_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let song = dir.join("Song.mp3").to_string_lossy().to_string();
+        for name in ["Song.mp3", "notes.txt", "cover.gif", "Song.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(find_sidecar(&song), None);
+
+        let found = |name: &str| {
+            std::fs::write(dir.join(name), b"").unwrap();
+            find_sidecar(&song).map(|image| Path::new(&image).file_name().unwrap().to_string_lossy().to_string())
+        };
+        assert_eq!(found("AlbumArt.jpg").as_deref(), Some("AlbumArt.jpg"));
+        assert_eq!(found("Front.PNG").as_deref(), Some("Front.PNG"));
+        assert_eq!(found("FOLDER.JPG").as_deref(), Some("FOLDER.JPG"));
+        assert_eq!(found("Cover.jpeg").as_deref(), Some("Cover.jpeg"));
+        assert_eq!(found("SONG.Png").as_deref(), Some("SONG.Png"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
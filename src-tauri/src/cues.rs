@@ -0,0 +1,228 @@
+use crate::bookmarks;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    /// WebVTT cue settings such as "line:0 align:start", kept as written
+    pub settings: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CueFormat {
+    Srt,
+    Vtt,
+}
+
+impl CueFormat {
+    pub fn from_extension(file: &str) -> Self {
+        if file.to_lowercase().ends_with(".srt") {
+            CueFormat::Srt
+        } else {
+            CueFormat::Vtt
+        }
+    }
+}
+
+/// Maps a time in the current subtitles to the time it should be shown at
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SyncPoint {
+    pub from: f64,
+    pub to: f64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Timing {
+    Offset(f64),
+    /// Shifts and scales linearly so that both points land on their target, fixing frame rate mismatches
+    Stretch(SyncPoint, SyncPoint),
+}
+
+/// Parses SRT or WebVTT. Blocks without a valid timing line, such as the WebVTT header, NOTE and STYLE blocks
+/// or a cue broken by hand editing, are skipped so that the rest of the file still shows.
+pub fn parse(content: &str) -> Vec<Cue> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content).replace("\r\n", "\n").replace('\r', "\n");
+
+    let mut cues = Vec::new();
+
+    for block in content.split("\n\n").map(|block| block.trim_matches('\n')).filter(|block| !block.is_empty()) {
+        let mut lines = block.lines();
+        let Some(timing) = lines.by_ref().take(2).find(|line| line.contains("-->")) else {
+            continue;
+        };
+
+        let (start, rest) = timing.split_once("-->").unwrap();
+        let rest = rest.trim();
+        let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+        let (Some(start), Some(end)) = (parse_time(start), parse_time(end)) else {
+            continue;
+        };
+
+        cues.push(Cue {
+            start,
+            end,
+            settings: settings.trim().to_string(),
+            text: lines.collect::<Vec<_>>().join("\n"),
+        });
+    }
+
+    cues
+}
+
+/// Accepts both "00:01:02,500" and "01:02.500"
+fn parse_time(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    if !value.contains(':') {
+        return None;
+    }
+    bookmarks::parse_timestamp(&value)
+}
+
+pub fn retime(cues: Vec<Cue>, timing: Timing) -> Result<Vec<Cue>, String> {
+    let map: Box<dyn Fn(f64) -> f64> = match timing {
+        Timing::Offset(offset) => {
+            if !offset.is_finite() {
+                return Err(format!("Invalid offset: {}", offset));
+            }
+            Box::new(move |time| time + offset)
+        }
+        Timing::Stretch(first, second) => {
+            let values = [first.from, first.to, second.from, second.to];
+            if values.iter().any(|value| !value.is_finite() || *value < 0.0) || (second.from - first.from).abs() < 0.001 {
+                return Err(String::from("Sync points must be two different non-negative times"));
+            }
+            let scale = (second.to - first.to) / (second.from - first.from);
+            if scale <= 0.0 {
+                return Err(String::from("Sync points must keep their order"));
+            }
+            Box::new(move |time| first.to + (time - first.from) * scale)
+        }
+    };
+
+    // Cues moved entirely before the start are dropped and ones that straddle it are cut
+    Ok(cues
+        .into_iter()
+        .filter_map(|cue| {
+            let end = map(cue.end);
+            (end > 0.0).then(|| Cue {
+                start: map(cue.start).max(0.0),
+                end,
+                ..cue
+            })
+        })
+        .collect())
+}
+
+pub fn write(cues: &[Cue], format: CueFormat) -> String {
+    match format {
+        CueFormat::Srt => cues.iter().enumerate().map(|(i, cue)| format!("{}\n{} --> {}\n{}\n\n", i + 1, format_time(cue.start, format), format_time(cue.end, format), cue.text)).collect(),
+        CueFormat::Vtt => {
+            let mut vtt = String::from("WEBVTT\n\n");
+            for cue in cues {
+                let settings = if cue.settings.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", cue.settings)
+                };
+                vtt.push_str(&format!("{} --> {}{}\n{}\n\n", format_time(cue.start, format), format_time(cue.end, format), settings, cue.text));
+            }
+            vtt
+        }
+    }
+}
+
+fn format_time(time: f64, format: CueFormat) -> String {
+    let timestamp = bookmarks::format_timestamp(time);
+    match format {
+        CueFormat::Srt => timestamp.replace('.', ","),
+        CueFormat::Vtt => timestamp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\r\n00:00:01,000 --> 00:00:02,500\r\nFirst line\r\nSecond line\r\n\r\n2\r\n00:01:00,250 --> 00:01:03,000\r\n<i>Next</i>\r\n";
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            settings: String::new(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_srt() {
+        assert_eq!(parse(&format!("\u{feff}{}", SRT)), [cue(1.0, 2.5, "First line\nSecond line"), cue(60.25, 63.0, "<i>Next</i>")]);
+    }
+
+    #[test]
+    fn parses_vtt_with_settings_and_skips_other_blocks() {
+        let vtt = "WEBVTT\n\nNOTE made by hand\n\nSTYLE\n::cue { color: yellow }\n\nintro\n00:01.000 --> 00:02.000 line:0 align:start\nTop\n";
+        let cues = parse(vtt);
+
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].settings, "line:0 align:start");
+        assert_eq!((cues[0].start, cues[0].end, cues[0].text.as_str()), (1.0, 2.0, "Top"));
+    }
+
+    #[test]
+    fn skips_broken_blocks() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nKept\n\n2\n00:00:xx,000 --> 00:00:04,000\nBroken\n\n3\n00:00:05,000 --> 00:00:06,000\nAlso kept\n";
+        assert_eq!(parse(srt), [cue(1.0, 2.0, "Kept"), cue(5.0, 6.0, "Also kept")]);
+    }
+
+    #[test]
+    fn converts_between_srt_and_vtt() {
+        let cues = parse(SRT);
+
+        let vtt = write(&cues, CueFormat::Vtt);
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nFirst line\nSecond line\n\n"));
+        assert_eq!(parse(&vtt), cues);
+
+        let srt = write(&parse(&vtt), CueFormat::Srt);
+        assert_eq!(srt, SRT.replace("\r\n", "\n") + "\n");
+    }
+
+    #[test]
+    fn offsets_and_cuts_cues_at_zero() {
+        let cues = vec![cue(1.0, 2.0, "Gone"), cue(2.5, 4.0, "Cut"), cue(10.0, 12.0, "Moved")];
+
+        let retimed = retime(cues, Timing::Offset(-3.0)).unwrap();
+        assert_eq!(retimed, [cue(0.0, 1.0, "Cut"), cue(7.0, 9.0, "Moved")]);
+
+        assert!(retime(Vec::new(), Timing::Offset(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn stretches_between_sync_points() {
+        let cues = vec![cue(10.0, 12.0, "A"), cue(100.0, 101.0, "B")];
+        let first = SyncPoint {
+            from: 10.0,
+            to: 11.0,
+        };
+        let second = SyncPoint {
+            from: 100.0,
+            to: 101.0 + 9.0,
+        };
+
+        let retimed = retime(cues, Timing::Stretch(first, second)).unwrap();
+        // Scaled by 1.1 around the first point
+        for (actual, expected) in [(retimed[0].start, 11.0), (retimed[0].end, 13.2), (retimed[1].start, 110.0)] {
+            assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+        }
+
+        assert!(retime(Vec::new(), Timing::Stretch(first, first)).is_err());
+        let backwards = SyncPoint {
+            from: 100.0,
+            to: 5.0,
+        };
+        assert!(retime(Vec::new(), Timing::Stretch(first, backwards)).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{
+    convert::{self, StreamSelector},
+    probe::{self, Probe, Stream},
+    shell,
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// CUE sheet INDEX times count frames of 1/75 second
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+const INVALID_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub enum AudioFormat {
+    /// Keeps the original codec in a container that can hold it
+    Copy,
+    Mp3,
+    Aac,
+    Opus,
+    Flac,
+    Wav,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum AudioSplit {
+    Chapters,
+    /// Path of the CUE sheet
+    Cue(String),
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioExtraction {
+    pub filePath: String,
+    pub outputDir: String,
+    pub audio: Option<StreamSelector>,
+    pub format: AudioFormat,
+    /// kbps, for mp3, aac and opus
+    pub bitrate: Option<u32>,
+    /// VBR level for mp3 (0 is best, 9 is smallest) or compression level for flac (0 to 12)
+    pub quality: Option<u32>,
+    pub split: Option<AudioSplit>,
+    pub cancellationToken: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Segment {
+    start: f64,
+    end: Option<f64>,
+    title: String,
+    performer: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CueSheet {
+    title: Option<String>,
+    performer: Option<String>,
+    segments: Vec<Segment>,
+}
+
+/// Writes the audio of the file to the output directory, as one file or one per chapter or CUE track, and returns the files written.
+/// A failed or cancelled piece removes the pieces written before it, so a split is never left half done.
+pub async fn extract(app: &AppHandle, request: AudioExtraction) -> Result<Vec<String>, String> {
+    let probe = probe::probe(app, &request.filePath).await?;
+    let stream = convert::select(&probe, "audio", request.audio.as_ref())?.ok_or_else(|| String::from("The file has no audio stream"))?;
+    let (extension, codec_args) = codec_args(&request, stream)?;
+
+    let stem = Path::new(&request.filePath).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let output_dir = Path::new(&request.outputDir);
+
+    let sheet = match &request.split {
+        None => None,
+        Some(AudioSplit::Chapters) => Some(from_chapters(&probe)?),
+        Some(AudioSplit::Cue(cue_path)) => Some(parse_cue(&std::fs::read_to_string(cue_path).map_err(|e| e.to_string())?)?),
+    };
+
+    let jobs: Vec<(PathBuf, Vec<String>)> = match sheet {
+        None => vec![(output_dir.join(format!("{}.{}", stem, extension)), Vec::new())],
+        Some(sheet) => {
+            let album = sheet.title.clone().or_else(|| probe.tag("album")).or_else(|| probe.tag("title")).unwrap_or_else(|| stem.clone());
+            let total = sheet.segments.len();
+            sheet
+                .segments
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| {
+                    let file_name = format!("{:02} - {}.{}", i + 1, sanitize_file_name(&segment.title, &stem), extension);
+                    (output_dir.join(file_name), segment_args(segment, &sheet, &album, i + 1, total))
+                })
+                .collect()
+        }
+    };
+
+    if let Some((existing, _)) = jobs.iter().find(|(output, _)| output.exists()) {
+        return Err(format!("{} already exists", existing.to_string_lossy()));
+    }
+
+    let mut written = Vec::new();
+
+    for (output, segment_args) in jobs {
+        let output = output.to_string_lossy().to_string();
+        let mut args: Vec<String> = ["-hide_banner", "-v", "error", "-i", &request.filePath].iter().map(|arg| arg.to_string()).collect();
+        args.extend(segment_args);
+        args.extend(["-map".to_string(), format!("0:{}", stream.index), "-vn".to_string(), "-map_metadata".to_string(), "0".to_string()]);
+        args.extend(codec_args.iter().cloned());
+        args.extend(["-n".to_string(), output.clone()]);
+
+        if let Err(e) = shell::exec_with_token(app, "ffmpeg", args, request.cancellationToken.clone()).await {
+            for file in written.iter().chain([&output]) {
+                let _ = std::fs::remove_file(file);
+            }
+            return Err(e);
+        }
+
+        written.push(output);
+    }
+
+    Ok(written)
+}
+
+fn codec_args(request: &AudioExtraction, stream: &Stream) -> Result<(&'static str, Vec<String>), String> {
+    let (extension, codec) = match request.format {
+        AudioFormat::Copy => (copy_container(&stream.codec_name), "copy"),
+        AudioFormat::Mp3 => ("mp3", "libmp3lame"),
+        AudioFormat::Aac => ("m4a", "aac"),
+        AudioFormat::Opus => ("opus", "libopus"),
+        AudioFormat::Flac => ("flac", "flac"),
+        AudioFormat::Wav => ("wav", "pcm_s16le"),
+    };
+
+    let mut args = vec!["-c:a".to_string(), codec.to_string()];
+
+    match (request.format, request.bitrate) {
+        (_, None) => {}
+        (AudioFormat::Mp3 | AudioFormat::Aac, Some(bitrate)) if (8..=640).contains(&bitrate) => args.extend(["-b:a".to_string(), format!("{}k", bitrate)]),
+        (AudioFormat::Opus, Some(bitrate)) if (6..=510).contains(&bitrate) => args.extend(["-b:a".to_string(), format!("{}k", bitrate)]),
+        (AudioFormat::Mp3 | AudioFormat::Aac | AudioFormat::Opus, Some(bitrate)) => return Err(format!("Invalid bitrate: {}k", bitrate)),
+        (format, Some(_)) => return Err(format!("{:?} does not take a bitrate", format)),
+    }
+
+    match (request.format, request.quality) {
+        (_, None) => {}
+        (AudioFormat::Mp3, Some(quality)) if quality <= 9 => args.extend(["-q:a".to_string(), quality.to_string()]),
+        (AudioFormat::Flac, Some(quality)) if quality <= 12 => args.extend(["-compression_level".to_string(), quality.to_string()]),
+        (AudioFormat::Mp3 | AudioFormat::Flac, Some(quality)) => return Err(format!("Invalid quality: {}", quality)),
+        (format, Some(_)) => return Err(format!("{:?} does not take a quality setting", format)),
+    }
+
+    Ok((extension, args))
+}
+
+/// Container for a stream copy, falling back to Matroska audio which holds any codec
+fn copy_container(codec_name: &str) -> &'static str {
+    match codec_name {
+        "aac" | "alac" => "m4a",
+        "mp3" => "mp3",
+        "opus" => "opus",
+        "vorbis" => "ogg",
+        "flac" => "flac",
+        "ac3" => "ac3",
+        "eac3" => "eac3",
+        codec if codec.starts_with("pcm_") => "wav",
+        _ => "mka",
+    }
+}
+
+fn segment_args(segment: &Segment, sheet: &CueSheet, album: &str, track: usize, total: usize) -> Vec<String> {
+    let mut args = vec!["-ss".to_string(), segment.start.to_string()];
+    if let Some(end) = segment.end {
+        args.extend(["-to".to_string(), end.to_string()]);
+    }
+
+    // Chapters of the whole file would be wrong in every piece
+    args.extend(["-map_chapters".to_string(), "-1".to_string()]);
+
+    let mut tags = vec![format!("title={}", segment.title), format!("album={}", album), format!("track={}/{}", track, total)];
+    if let Some(performer) = segment.performer.as_ref().or(sheet.performer.as_ref()) {
+        tags.push(format!("artist={}", performer));
+    }
+    if let Some(performer) = &sheet.performer {
+        tags.push(format!("album_artist={}", performer));
+    }
+
+    for tag in tags {
+        args.extend(["-metadata".to_string(), tag]);
+    }
+
+    args
+}
+
+fn from_chapters(probe: &Probe) -> Result<CueSheet, String> {
+    if probe.chapters.is_empty() {
+        return Err(String::from("The file has no chapters"));
+    }
+
+    Ok(CueSheet {
+        title: None,
+        performer: None,
+        segments: probe
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(i, chapter)| Segment {
+                start: chapter.start,
+                end: Some(chapter.end).filter(|end| *end > chapter.start),
+                title: if chapter.title.is_empty() {
+                    format!("Chapter {}", i + 1)
+                } else {
+                    chapter.title.clone()
+                },
+                performer: None,
+            })
+            .collect(),
+    })
+}
+
+/// Reads the TITLE, PERFORMER, TRACK and INDEX 01 commands. Each track ends where the next one starts.
+fn parse_cue(content: &str) -> Result<CueSheet, String> {
+    let mut sheet = CueSheet::default();
+    let mut in_track = false;
+
+    for line in content.lines().map(str::trim) {
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = rest.trim().trim_matches('"').to_string();
+
+        match command.to_uppercase().as_str() {
+            "TRACK" => {
+                in_track = true;
+                sheet.segments.push(Segment {
+                    start: -1.0,
+                    ..Default::default()
+                });
+            }
+            "TITLE" if in_track => sheet.segments.last_mut().unwrap().title = value,
+            "TITLE" => sheet.title = Some(value),
+            "PERFORMER" if in_track => sheet.segments.last_mut().unwrap().performer = Some(value),
+            "PERFORMER" => sheet.performer = Some(value),
+            "INDEX" if in_track => {
+                let Some(("01", time)) = rest.trim().split_once(char::is_whitespace) else {
+                    continue;
+                };
+                sheet.segments.last_mut().unwrap().start = parse_cue_time(time.trim()).ok_or_else(|| format!("Invalid CUE index: {}", line))?;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(i) = sheet.segments.iter().position(|segment| segment.start < 0.0) {
+        return Err(format!("CUE track {} has no INDEX 01", i + 1));
+    }
+
+    if sheet.segments.is_empty() {
+        return Err(String::from("The CUE sheet has no tracks"));
+    }
+
+    let starts: Vec<f64> = sheet.segments.iter().skip(1).map(|segment| segment.start).collect();
+    for (i, segment) in sheet.segments.iter_mut().enumerate() {
+        segment.end = starts.get(i).copied();
+        if segment.title.is_empty() {
+            segment.title = format!("Track {}", i + 1);
+        }
+    }
+
+    Ok(sheet)
+}
+
+/// "mm:ss:ff", where minutes may go past 59 on long discs
+fn parse_cue_time(value: &str) -> Option<f64> {
+    let parts: Vec<u32> = value.split(':').map(|part| part.parse::<u32>().ok()).collect::<Option<_>>()?;
+    let [minutes, seconds, frames] = parts[..] else {
+        return None;
+    };
+    if seconds >= 60 || frames as f64 >= CUE_FRAMES_PER_SECOND {
+        return None;
+    }
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / CUE_FRAMES_PER_SECOND)
+}
+
+fn sanitize_file_name(name: &str, fallback: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|char| {
+            if INVALID_FILE_NAME_CHARS.contains(&char) || char.is_control() {
+                '_'
+            } else {
+                char
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches('.');
+    if sanitized.is_empty() {
+        fallback.to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE Jazz
+PERFORMER "The Band"
+TITLE "Live Album"
+FILE "live.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Opening"
+    INDEX 00 00:00:00
+    INDEX 01 00:00:32
+  TRACK 02 AUDIO
+    TITLE "Second"
+    PERFORMER "Guest"
+    INDEX 01 04:10:00
+  TRACK 03 AUDIO
+    INDEX 01 75:00:74
+"#;
+
+    #[test]
+    fn parses_cue_times() {
+        assert_eq!(parse_cue_time("00:00:00"), Some(0.0));
+        assert_eq!(parse_cue_time("01:02:15"), Some(62.2));
+        assert_eq!(parse_cue_time("99:59:74"), Some(99.0 * 60.0 + 59.0 + 74.0 / 75.0));
+        assert_eq!(parse_cue_time("00:60:00"), None);
+        assert_eq!(parse_cue_time("00:00:75"), None);
+        assert_eq!(parse_cue_time("00:00"), None);
+        assert_eq!(parse_cue_time("00:0a:00"), None);
+    }
+
+    #[test]
+    fn parses_cue_sheets() {
+        let sheet = parse_cue(SHEET).unwrap();
+
+        assert_eq!(sheet.title.as_deref(), Some("Live Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+
+        let segments: Vec<(f64, Option<f64>, &str, Option<&str>)> = sheet.segments.iter().map(|segment| (segment.start, segment.end, segment.title.as_str(), segment.performer.as_deref())).collect();
+        let third = 75.0 * 60.0 + 74.0 / 75.0;
+        assert_eq!(segments, [(32.0 / 75.0, Some(250.0), "Opening", None), (250.0, Some(third), "Second", Some("Guest")), (third, None, "Track 3", None),]);
+    }
+
+    #[test]
+    fn rejects_incomplete_cue_sheets() {
+        assert!(parse_cue("TITLE \"Nothing\"").is_err());
+        assert!(parse_cue("TRACK 01 AUDIO\nINDEX 00 00:00:00").is_err());
+        assert!(parse_cue("TRACK 01 AUDIO\nINDEX 01 00:00:99").is_err());
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize_file_name("AC/DC: Live?", "album"), "AC_DC_ Live_");
+        assert_eq!(sanitize_file_name(" ... ", "album"), "album");
+    }
+}
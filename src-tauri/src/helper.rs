@@ -1,4 +1,3 @@
-use crate::Sort;
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager};
 
@@ -36,15 +35,3 @@ pub fn get_init_args(app: &AppHandle) -> Vec<String> {
     let mut urls = state.lock().unwrap();
     urls.take()
 }
-
-pub fn set_sort(app: &AppHandle, new_sort: Sort) {
-    if let Some(sort) = app.try_state::<Mutex<Sort>>() {
-        *sort.lock().unwrap() = new_sort;
-    } else {
-        app.manage(Mutex::new(new_sort));
-    }
-}
-
-pub fn get_sort(app: &AppHandle) -> Option<Sort> {
-    app.try_state::<Mutex<Sort>>().map(|sort| sort.lock().unwrap().clone())
-}
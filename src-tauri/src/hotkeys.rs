@@ -0,0 +1,205 @@
+use crate::{keymap, settings, PLAYER};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+const GLOBAL_SHORTCUT_EVENT_NAME: &str = "global-shortcut";
+
+pub const GLOBAL_SHORTCUT_ACTIONS: [(&str, &str); 8] = [
+    ("PlayPause", "MediaPlayPause"),
+    ("Next", "MediaTrackNext"),
+    ("Previous", "MediaTrackPrevious"),
+    // Left unbound, as desktops and editors commonly use Ctrl+Alt+Arrow themselves
+    ("SeekForward", ""),
+    ("SeekBackward", ""),
+    ("VolumeUp", ""),
+    ("VolumeDown", ""),
+    ("TogglePlaylist", "Ctrl+Alt+P"),
+];
+
+pub fn default_bindings() -> BTreeMap<String, String> {
+    GLOBAL_SHORTCUT_ACTIONS.iter().map(|(action, accelerator)| (action.to_string(), accelerator.to_string())).collect()
+}
+
+/// Checks that every binding names a known action and an accelerator the OS layer can parse. Empty accelerators disable the action.
+pub fn validate(bindings: &BTreeMap<String, String>) -> Result<(), String> {
+    for (action, accelerator) in bindings {
+        if !GLOBAL_SHORTCUT_ACTIONS.iter().any(|(name, _)| name == action) {
+            return Err(format!("Unknown global shortcut action: {}", action));
+        }
+
+        if !accelerator.is_empty() {
+            accelerator.parse::<Shortcut>().map_err(|e| format!("Invalid global shortcut for {}: {} ({})", action, accelerator, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutConflict {
+    pub accelerator: String,
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutFailure {
+    pub action: String,
+    pub accelerator: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ShortcutStatus {
+    pub registered: Vec<String>,
+    pub conflicts: Vec<ShortcutConflict>,
+    pub failed: Vec<ShortcutFailure>,
+}
+
+#[derive(Default)]
+pub struct Hotkeys {
+    actions: HashMap<u32, String>,
+    shortcuts: Vec<Shortcut>,
+    status: ShortcutStatus,
+}
+
+pub fn setup(app: &tauri::App) -> tauri::Result<()> {
+    app.manage(Mutex::new(Hotkeys::default()));
+    app.handle().plugin(tauri_plugin_global_shortcut::Builder::new().with_handler(on_shortcut).build())?;
+    register(app.handle());
+    Ok(())
+}
+
+fn on_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+
+    let state = app.state::<Mutex<Hotkeys>>();
+    let action = state.lock().unwrap().actions.get(&shortcut.id()).cloned();
+
+    if let Some(action) = action {
+        let _ = app.emit_to(
+            EventTarget::WebviewWindow {
+                label: PLAYER.to_string(),
+            },
+            GLOBAL_SHORTCUT_EVENT_NAME,
+            action,
+        );
+    }
+}
+
+/// Replaces every registration with the bindings currently in settings
+pub fn register(app: &AppHandle) {
+    let bindings = settings::get(app).globalShortcuts;
+    let state = app.state::<Mutex<Hotkeys>>();
+    let mut hotkeys = state.lock().unwrap();
+
+    for shortcut in hotkeys.shortcuts.drain(..) {
+        let _ = app.global_shortcut().unregister(shortcut);
+    }
+    hotkeys.actions.clear();
+
+    let mut status = ShortcutStatus {
+        conflicts: find_conflicts(&bindings),
+        ..Default::default()
+    };
+
+    // The desktop already sends media keys over MPRIS, so grabbing them as well would toggle twice
+    let skip_media_keys = media_keys_handled(app);
+
+    for (action, accelerator) in bindings.iter().filter(|(_, accelerator)| !accelerator.is_empty()).filter(|(_, accelerator)| !(skip_media_keys && is_media_key(accelerator))) {
+        let result = accelerator.parse::<Shortcut>().map_err(|e| e.to_string()).and_then(|shortcut| {
+            if hotkeys.actions.contains_key(&shortcut.id()) {
+                return Err(String::from("Already bound to another action"));
+            }
+            app.global_shortcut().register(shortcut).map(|_| shortcut).map_err(|e| e.to_string())
+        });
+
+        match result {
+            Ok(shortcut) => {
+                hotkeys.actions.insert(shortcut.id(), action.clone());
+                hotkeys.shortcuts.push(shortcut);
+                status.registered.push(action.clone());
+            }
+            Err(error) => status.failed.push(ShortcutFailure {
+                action: action.clone(),
+                accelerator: accelerator.clone(),
+                error,
+            }),
+        }
+    }
+
+    hotkeys.status = status;
+}
+
+#[cfg(target_os = "linux")]
+fn media_keys_handled(app: &AppHandle) -> bool {
+    crate::mpris::is_active(app)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn media_keys_handled(_app: &AppHandle) -> bool {
+    false
+}
+
+fn is_media_key(accelerator: &str) -> bool {
+    accelerator.trim().to_lowercase().starts_with("media")
+}
+
+fn find_conflicts(bindings: &BTreeMap<String, String>) -> Vec<ShortcutConflict> {
+    let mut by_accelerator: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (action, accelerator) in bindings.iter().filter(|(_, accelerator)| !accelerator.is_empty()) {
+        // Media keys are not keymap keys, so they only compare by name
+        let key = keymap::normalize(accelerator).unwrap_or_else(|_| accelerator.trim().to_lowercase());
+        by_accelerator.entry(key).or_default().push(action.clone());
+    }
+
+    by_accelerator
+        .into_iter()
+        .filter(|(_, actions)| actions.len() > 1)
+        .map(|(accelerator, actions)| ShortcutConflict {
+            accelerator,
+            actions,
+        })
+        .collect()
+}
+
+pub fn status(app: &AppHandle) -> ShortcutStatus {
+    app.state::<Mutex<Hotkeys>>().lock().unwrap().status.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(action, accelerator)| (action.to_string(), accelerator.to_string())).collect()
+    }
+
+    #[test]
+    fn conflicts_compare_normalized_accelerators() {
+        let conflicts = find_conflicts(&bindings(&[("Next", "alt+ctrl+n"), ("Previous", "Ctrl + Alt + N"), ("PlayPause", "MediaPlayPause"), ("TogglePlaylist", "mediaplaypause"), ("VolumeUp", "")]));
+
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].accelerator, "Ctrl+Alt+N");
+        assert_eq!(conflicts[0].actions, vec!["Next", "Previous"]);
+        assert_eq!(conflicts[1].actions, vec!["PlayPause", "TogglePlaylist"]);
+    }
+
+    #[test]
+    fn defaults_leave_ctrl_alt_arrows_unbound() {
+        let defaults = default_bindings();
+
+        assert_eq!(validate(&defaults), Ok(()));
+        assert!(find_conflicts(&defaults).is_empty());
+        for action in ["SeekForward", "SeekBackward", "VolumeUp", "VolumeDown"] {
+            assert_eq!(defaults[action], "");
+        }
+        assert!(is_media_key(&defaults["PlayPause"]) && !is_media_key(&defaults["TogglePlaylist"]));
+    }
+}
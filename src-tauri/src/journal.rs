@@ -0,0 +1,323 @@
+use crate::{cache, trash};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::VecDeque, path::Path, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovedFile {
+    pub from: String,
+    pub to: String,
+    /// A file that was at the destination and went to the trash
+    #[serde(default)]
+    pub replaced: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FileOperation {
+    Trash(Vec<String>),
+    Move(Vec<MovedFile>),
+    Rename(MovedFile),
+}
+
+impl FileOperation {
+    /// Files the operation moves in the direction, from where they are to where they go. Trashed files are not followed.
+    fn moves(&self, direction: Direction) -> Vec<MovedFile> {
+        let files = match self {
+            FileOperation::Trash(_) => return Vec::new(),
+            FileOperation::Move(files) => files.clone(),
+            FileOperation::Rename(file) => vec![file.clone()],
+        };
+
+        match direction {
+            Direction::Apply => files,
+            Direction::Revert => files
+                .into_iter()
+                .map(|file| MovedFile {
+                    from: file.to,
+                    to: file.from,
+                    replaced: file.replaced,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What an undo or redo changed, with the first error when part of it could not be done
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalResult {
+    pub done: Option<FileOperation>,
+    pub error: Option<String>,
+}
+
+/// File operations done in this session. Lives in the backend so it survives webview reloads.
+#[derive(Default)]
+pub struct Journal {
+    undo: VecDeque<FileOperation>,
+    redo: Vec<FileOperation>,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Mutex::new(Journal::default()));
+}
+
+pub fn record(app: &AppHandle, operation: FileOperation) {
+    let state = app.state::<Mutex<Journal>>();
+    let mut journal = state.lock().unwrap();
+
+    if journal.undo.len() == MAX_HISTORY {
+        journal.undo.pop_front();
+    }
+    journal.undo.push_back(operation);
+    journal.redo.clear();
+}
+
+/// Reverts the last operation off the main thread. Files that could not be reverted stay on the undo stack, the rest move to the redo stack.
+pub async fn undo(app: &AppHandle) -> Result<JournalResult, String> {
+    let Some(operation) = app.state::<Mutex<Journal>>().lock().unwrap().undo.pop_back() else {
+        return Ok(JournalResult {
+            done: None,
+            error: None,
+        });
+    };
+
+    let keys = cache::file_keys(operation.moves(Direction::Revert).iter().map(|file| file.from.as_str()));
+    let outcome = tauri::async_runtime::spawn_blocking(move || run(operation, Direction::Revert)).await.map_err(|e| e.to_string())?;
+    if let Some(done) = &outcome.done {
+        cache::rekey_moved(app, &keys, &done.moves(Direction::Revert));
+    }
+
+    let state = app.state::<Mutex<Journal>>();
+    let mut journal = state.lock().unwrap();
+    if let Some(failed) = outcome.failed {
+        journal.undo.push_back(failed);
+    }
+    if let Some(done) = &outcome.done {
+        journal.redo.push(done.clone());
+    }
+
+    Ok(JournalResult {
+        done: outcome.done,
+        error: outcome.error,
+    })
+}
+
+/// Applies the last undone operation again, splitting it the same way as undo
+pub async fn redo(app: &AppHandle) -> Result<JournalResult, String> {
+    let Some(operation) = app.state::<Mutex<Journal>>().lock().unwrap().redo.pop() else {
+        return Ok(JournalResult {
+            done: None,
+            error: None,
+        });
+    };
+
+    let keys = cache::file_keys(operation.moves(Direction::Apply).iter().map(|file| file.from.as_str()));
+    let outcome = tauri::async_runtime::spawn_blocking(move || run(operation, Direction::Apply)).await.map_err(|e| e.to_string())?;
+    if let Some(done) = &outcome.done {
+        cache::rekey_moved(app, &keys, &done.moves(Direction::Apply));
+    }
+
+    let state = app.state::<Mutex<Journal>>();
+    let mut journal = state.lock().unwrap();
+    if let Some(failed) = outcome.failed {
+        journal.redo.push(failed);
+    }
+    if let Some(done) = &outcome.done {
+        journal.undo.push_back(done.clone());
+    }
+
+    Ok(JournalResult {
+        done: outcome.done,
+        error: outcome.error,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Apply,
+    Revert,
+}
+
+/// Parts of an operation that were done and that failed, with the first error
+struct Outcome {
+    done: Option<FileOperation>,
+    failed: Option<FileOperation>,
+    error: Option<String>,
+}
+
+fn run(operation: FileOperation, direction: Direction) -> Outcome {
+    let revert = direction == Direction::Revert;
+
+    match operation {
+        FileOperation::Trash(files) => {
+            let (done, failed, error) = split(files, false, |file| {
+                if revert {
+                    trash::restore(Path::new(file))
+                } else {
+                    trash::trash(file)
+                }
+            });
+            Outcome {
+                done: (!done.is_empty()).then_some(FileOperation::Trash(done)),
+                failed: (!failed.is_empty()).then_some(FileOperation::Trash(failed)),
+                error,
+            }
+        }
+        // Moves are reverted last first, so that a file moved twice goes back through the same names
+        FileOperation::Move(files) => {
+            // A replaced file comes back from the trash once the moved file is out of its place. The move itself counts as done when that fails.
+            let restore_error = RefCell::new(None);
+            let (done, failed, error) = split(files, revert, |file| {
+                if revert {
+                    move_file(&file.to, &file.from)?;
+                    if file.replaced {
+                        if let Err(e) = trash::restore(Path::new(&file.to)) {
+                            restore_error.borrow_mut().get_or_insert(e);
+                        }
+                    }
+                    Ok(())
+                } else {
+                    if file.replaced && Path::new(&file.to).exists() {
+                        trash::trash(&file.to)?;
+                    }
+                    move_file(&file.from, &file.to)
+                }
+            });
+            Outcome {
+                done: (!done.is_empty()).then_some(FileOperation::Move(done)),
+                failed: (!failed.is_empty()).then_some(FileOperation::Move(failed)),
+                error: error.or(restore_error.into_inner()),
+            }
+        }
+        FileOperation::Rename(file) => {
+            let result = if revert {
+                move_file(&file.to, &file.from)
+            } else {
+                move_file(&file.from, &file.to)
+            };
+            match result {
+                Ok(_) => Outcome {
+                    done: Some(FileOperation::Rename(file)),
+                    failed: None,
+                    error: None,
+                },
+                Err(e) => Outcome {
+                    done: None,
+                    failed: Some(FileOperation::Rename(file)),
+                    error: Some(e),
+                },
+            }
+        }
+    }
+}
+
+/// Runs the step on every item, keeping the original order in both halves
+fn split<T, F>(items: Vec<T>, reverse: bool, step: F) -> (Vec<T>, Vec<T>, Option<String>)
+where
+    F: Fn(&T) -> Result<(), String>,
+{
+    let mut results: Vec<(T, Result<(), String>)> = Vec::with_capacity(items.len());
+    if reverse {
+        for item in items.into_iter().rev() {
+            let result = step(&item);
+            results.push((item, result));
+        }
+        results.reverse();
+    } else {
+        for item in items {
+            let result = step(&item);
+            results.push((item, result));
+        }
+    }
+
+    let mut done = Vec::new();
+    let mut failed = Vec::new();
+    let mut error = None;
+    for (item, result) in results {
+        match result {
+            Ok(_) => done.push(item),
+            Err(e) => {
+                error.get_or_insert(e);
+                failed.push(item);
+            }
+        }
+    }
+
+    (done, failed, error)
+}
+
+fn move_file(from: &str, to: &str) -> Result<(), String> {
+    if Path::new(to).exists() && !is_same_path(from, to) {
+        return Err(format!("{} already exists", to));
+    }
+
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to).map_err(|e| e.to_string())?;
+    std::fs::remove_file(from).map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn is_same_path(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_same_path(a: &str, b: &str) -> bool {
+    a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(dir: &Path, from: &str, to: &str) -> MovedFile {
+        MovedFile {
+            from: dir.join(from).to_string_lossy().to_string(),
+            to: dir.join(to).to_string_lossy().to_string(),
+            replaced: false,
+        }
+    }
+
+    #[test]
+    fn revert_keeps_only_the_files_that_failed() {
+        let dir = std::env::temp_dir().join(format!("journal_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a2"), b"a").unwrap();
+        std::fs::write(dir.join("b2"), b"b").unwrap();
+        // Blocks the revert of b2
+        std::fs::write(dir.join("b"), b"other").unwrap();
+
+        let outcome = run(FileOperation::Move(vec![moved(&dir, "a", "a2"), moved(&dir, "b", "b2")]), Direction::Revert);
+
+        assert!(matches!(&outcome.done, Some(FileOperation::Move(files)) if files.len() == 1 && files[0].from.ends_with("a")));
+        assert!(matches!(&outcome.failed, Some(FileOperation::Move(files)) if files.len() == 1 && files[0].from.ends_with("b")));
+        assert!(outcome.error.is_some());
+        assert!(dir.join("a").exists() && dir.join("b2").exists());
+
+        // Retrying the failed part no longer touches the file that was already reverted
+        std::fs::remove_file(dir.join("b")).unwrap();
+        let retry = run(outcome.failed.unwrap(), Direction::Revert);
+        assert!(retry.error.is_none() && retry.failed.is_none());
+        assert!(dir.join("b").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reverts_chained_moves_in_reverse_order() {
+        let dir = std::env::temp_dir().join(format!("journal_chain_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("c"), b"x").unwrap();
+
+        let outcome = run(FileOperation::Move(vec![moved(&dir, "a", "b"), moved(&dir, "b", "c")]), Direction::Revert);
+
+        assert!(outcome.error.is_none());
+        assert!(dir.join("a").exists() && !dir.join("c").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    menu::{self, PLAYER, PLAY_LIST},
+    settings,
+};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter};
+
+/// Accelerators by renderer name, then by command id
+pub type Keymap = BTreeMap<String, BTreeMap<String, String>>;
+
+const KEYMAP_CHANGED_EVENT: &str = "keymap-changed";
+const MODIFIERS: [&str; 4] = ["Ctrl", "Alt", "Shift", "Meta"];
+const NAMED_KEYS: [&str; 15] = ["Delete", "Backspace", "Enter", "Escape", "Tab", "Space", "Insert", "Home", "End", "PageUp", "PageDown", "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight"];
+
+const DEFAULT_KEYMAP: [(&str, &str, &str); 19] = [
+    (PLAYER, "TogglePlaylistWindow", "Ctrl+P"),
+    (PLAYER, "ToggleFullscreen", "F11"),
+    (PLAYER, "Capture", "Ctrl+S"),
+    (PLAYER, "ViewSettingsJson", "Ctrl+Shift+P"),
+    (PLAYER, "NextChapter", "PageDown"),
+    (PLAYER, "PreviousChapter", "PageUp"),
+    (PLAYER, "DelaySubtitles", "H"),
+    (PLAYER, "AdvanceSubtitles", "G"),
+    (PLAYER, "SetLoopStart", "["),
+    (PLAYER, "SetLoopEnd", "]"),
+    (PLAY_LIST, "Remove", "Delete"),
+    (PLAY_LIST, "Trash", "Shift+Delete"),
+    (PLAY_LIST, "CopyFileName", "Ctrl+C"),
+    (PLAY_LIST, "CopyFullpath", "Ctrl+Shift+C"),
+    (PLAY_LIST, "PasteFilePath", "Ctrl+V"),
+    (PLAY_LIST, "Reveal", "Ctrl+R"),
+    (PLAY_LIST, "Rename", "F2"),
+    (PLAY_LIST, "Undo", "Ctrl+Z"),
+    (PLAY_LIST, "Redo", "Ctrl+Y"),
+];
+
+/// Converts an accelerator to the canonical "Ctrl+Alt+Shift+Meta+Key" form shared with the renderers
+pub fn normalize(accelerator: &str) -> Result<String, String> {
+    let parts: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+    let Some((key, modifiers)) = parts.split_last() else {
+        return Err(String::from("Empty accelerator"));
+    };
+
+    let mut pressed = [false; MODIFIERS.len()];
+    for modifier in modifiers {
+        let index = match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" | "cmdorctrl" => 0,
+            "alt" | "option" => 1,
+            "shift" => 2,
+            "meta" | "super" | "cmd" | "command" => 3,
+            _ => return Err(format!("Unknown modifier in {}: {}", accelerator, modifier)),
+        };
+
+        if pressed[index] {
+            return Err(format!("Duplicate modifier in {}: {}", accelerator, modifier));
+        }
+        pressed[index] = true;
+    }
+
+    let key = normalize_key(key).ok_or_else(|| format!("Unknown key in {}: {}", accelerator, key))?;
+
+    let mut normalized: Vec<&str> = MODIFIERS.iter().zip(pressed).filter(|(_, pressed)| *pressed).map(|(modifier, _)| *modifier).collect();
+    normalized.push(&key);
+
+    Ok(normalized.join("+"))
+}
+
+fn normalize_key(key: &str) -> Option<String> {
+    let mut chars = key.chars();
+    if let (Some(char), None) = (chars.next(), chars.next()) {
+        return if char.is_ascii_graphic() {
+            Some(char.to_ascii_uppercase().to_string())
+        } else {
+            None
+        };
+    }
+
+    if let Some(number) = key.strip_prefix(['F', 'f']).and_then(|number| number.parse::<u8>().ok()) {
+        return if (1..=24).contains(&number) {
+            Some(format!("F{}", number))
+        } else {
+            None
+        };
+    }
+
+    NAMED_KEYS.iter().find(|name| name.eq_ignore_ascii_case(key)).map(|name| name.to_string())
+}
+
+/// Applies user overrides on top of the defaults. An empty override unbinds the command.
+pub fn resolve(overrides: &Keymap) -> Keymap {
+    let mut keymap = Keymap::new();
+
+    for (scope, id, default_accelerator) in DEFAULT_KEYMAP {
+        let accelerator = match overrides.get(scope).and_then(|bindings| bindings.get(id)) {
+            Some(accelerator) if accelerator.is_empty() => continue,
+            Some(accelerator) => normalize(accelerator).unwrap_or_else(|_| default_accelerator.to_string()),
+            None => default_accelerator.to_string(),
+        };
+
+        keymap.entry(scope.to_string()).or_default().insert(id.to_string(), accelerator);
+    }
+
+    keymap
+}
+
+/// Rejects unknown commands, unparsable accelerators and accelerators assigned to more than one command in the same window
+pub fn validate(overrides: &Keymap) -> Result<(), String> {
+    for (scope, bindings) in overrides {
+        for (id, accelerator) in bindings {
+            if !DEFAULT_KEYMAP.iter().any(|(default_scope, default_id, _)| default_scope == scope && default_id == id) {
+                return Err(format!("Unknown shortcut command: {}.{}", scope, id));
+            }
+
+            if !accelerator.is_empty() {
+                normalize(accelerator)?;
+            }
+        }
+    }
+
+    for (scope, bindings) in resolve(overrides) {
+        let mut assigned: BTreeMap<&str, &str> = BTreeMap::new();
+        for (id, accelerator) in &bindings {
+            if let Some(other) = assigned.insert(accelerator, id) {
+                return Err(format!("{} is assigned to both {} and {} in {}", accelerator, other, id, scope));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn accelerator<'a>(keymap: &'a Keymap, scope: &str, id: &str) -> Option<&'a str> {
+    keymap.get(scope).and_then(|bindings| bindings.get(id)).map(String::as_str)
+}
+
+pub fn get(app: &AppHandle) -> Keymap {
+    resolve(&settings::get(app).keymap)
+}
+
+/// Rebuilds the menus so they display the active bindings and sends the new keymap to the renderers
+pub fn apply(app: &AppHandle) -> Result<(), String> {
+    let settings = settings::get(app);
+    menu::refresh(app, &settings);
+    app.emit(KEYMAP_CHANGED_EVENT, resolve(&settings.keymap)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(scope: &str, id: &str, accelerator: &str) -> Keymap {
+        Keymap::from([(scope.to_string(), BTreeMap::from([(id.to_string(), accelerator.to_string())]))])
+    }
+
+    #[test]
+    fn normalize_gives_the_canonical_form() {
+        assert_eq!(normalize("shift+ctrl+p"), Ok(String::from("Ctrl+Shift+P")));
+        assert_eq!(normalize("Command + Option + 1"), Ok(String::from("Alt+Meta+1")));
+        assert_eq!(normalize("CmdOrCtrl+["), Ok(String::from("Ctrl+[")));
+        assert_eq!(normalize("f11"), Ok(String::from("F11")));
+        assert_eq!(normalize("pagedown"), Ok(String::from("PageDown")));
+        assert_eq!(normalize("Alt+arrowleft"), Ok(String::from("Alt+ArrowLeft")));
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_accelerators() {
+        for accelerator in ["", "Ctrl+", "Hyper+P", "Ctrl+Control+P", "F25", "F0", "Ctrl+Left", "Ctrl+é"] {
+            assert!(normalize(accelerator).is_err(), "{}", accelerator);
+        }
+    }
+
+    #[test]
+    fn overrides_replace_or_unbind_defaults() {
+        let keymap = resolve(&overrides(PLAYER, "Capture", "shift+s"));
+        assert_eq!(accelerator(&keymap, PLAYER, "Capture"), Some("Shift+S"));
+        assert_eq!(accelerator(&keymap, PLAY_LIST, "Rename"), Some("F2"));
+
+        let keymap = resolve(&overrides(PLAY_LIST, "Rename", ""));
+        assert_eq!(accelerator(&keymap, PLAY_LIST, "Rename"), None);
+    }
+
+    #[test]
+    fn validate_rejects_unknown_commands_and_duplicates() {
+        assert!(validate(&overrides(PLAYER, "Unknown", "Ctrl+U")).is_err());
+        assert!(validate(&overrides(PLAYER, "Capture", "Ctrl+Bad")).is_err());
+        // Same key as the default of TogglePlaylistWindow
+        assert!(validate(&overrides(PLAYER, "Capture", "ctrl+p")).is_err());
+        // Same key in another window is fine
+        assert_eq!(validate(&overrides(PLAYER, "Capture", "Ctrl+C")), Ok(()));
+        assert_eq!(validate(&Keymap::new()), Ok(()));
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
-use std::{env, path::PathBuf};
+use std::path::{Path, PathBuf};
 #[cfg(target_os = "windows")]
 use tauri::Emitter;
 use tauri::{Manager, WebviewWindow, WindowEvent};
@@ -8,17 +8,37 @@ use zouni::{
     dialog::{FileDialogResult, MessageResult},
     ClipboardData, FileAttribute, Operation,
 };
+mod ab_loop;
+mod bookmarks;
+mod cache;
+mod chapters;
+mod convert;
+mod cover;
+mod cues;
 mod dialog;
+mod extract;
 mod helper;
+mod hotkeys;
+mod journal;
+mod keymap;
+mod locale;
+mod loudness;
 mod menu;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod playlist;
+mod probe;
+mod scenes;
+mod settings;
 mod shell;
-
-#[allow(non_snake_case)]
-#[derive(Serialize, Deserialize, Default, Clone)]
-struct Sort {
-    order: String,
-    groupBy: bool,
-}
+mod silence;
+mod storage;
+mod subtitles;
+mod tag_rename;
+mod tags;
+mod transfer;
+mod trash;
+mod waveform;
 
 static PLAYER: &str = "Player";
 static PLAY_LIST: &str = "Playlist";
@@ -42,17 +62,18 @@ fn get_init_args(app: tauri::AppHandle) -> Vec<String> {
 }
 
 #[tauri::command]
-fn set_sort(app: tauri::AppHandle, payload: Sort) {
-    helper::set_sort(&app, payload);
+fn set_sort(app: tauri::AppHandle, payload: settings::Sort) -> Result<(), String> {
+    settings::update(&app, |settings| settings.sort = payload).map(|_| ())
 }
 
 #[tauri::command]
-fn get_sort(app: tauri::AppHandle) -> Option<Sort> {
-    helper::get_sort(&app)
+fn get_sort(app: tauri::AppHandle) -> Option<settings::Sort> {
+    Some(settings::get(&app).sort)
 }
 
 #[tauri::command]
-fn change_theme(window: WebviewWindow, payload: String) {
+fn change_theme(window: WebviewWindow, payload: String) -> Result<(), String> {
+    settings::update(window.app_handle(), |settings| settings.theme = payload.clone())?;
     let (tauri_them, menu_theme) = match payload.as_str() {
         "dark" => (tauri::Theme::Dark, wcpopup::config::Theme::Dark),
         "light" => (tauri::Theme::Light, wcpopup::config::Theme::Light),
@@ -60,6 +81,7 @@ fn change_theme(window: WebviewWindow, payload: String) {
     };
     let _ = window.set_theme(Some(tauri_them));
     menu::change_menu_theme(window.app_handle(), menu_theme);
+    Ok(())
 }
 
 #[tauri::command]
@@ -77,14 +99,21 @@ async fn open_sort_context_menu(window: WebviewWindow, payload: menu::Position)
     menu::popup_menu(window.app_handle(), window.label(), menu::SORT_MENU_NAME, payload).await;
 }
 
+#[tauri::command]
+fn update_menu(app: tauri::AppHandle, payload: menu::MenuUpdate) -> Result<(), String> {
+    menu::update(&app, payload, &settings::get(&app))
+}
+
 #[tauri::command]
 fn reveal(payload: String) -> Result<(), String> {
     shell::reveal(payload)
 }
 
 #[tauri::command]
-fn trash(payload: String) -> Result<(), String> {
-    zouni::fs::trash(payload)
+fn trash(app: tauri::AppHandle, payload: String) -> Result<(), String> {
+    trash::trash(&payload)?;
+    journal::record(&app, journal::FileOperation::Trash(vec![payload]));
+    Ok(())
 }
 
 #[tauri::command]
@@ -98,8 +127,261 @@ struct RenameInfo {
     old: String,
 }
 #[tauri::command]
-fn rename(payload: RenameInfo) -> Result<(), String> {
-    std::fs::rename(payload.old, payload.new).map_err(|e| e.to_string())
+fn rename(app: tauri::AppHandle, payload: RenameInfo) -> Result<(), String> {
+    let keys = cache::file_keys([payload.old.as_str()]);
+    std::fs::rename(&payload.old, &payload.new).map_err(|e| e.to_string())?;
+    let file = journal::MovedFile {
+        from: payload.old,
+        to: payload.new,
+        replaced: false,
+    };
+    cache::rekey_moved(&app, &keys, std::slice::from_ref(&file));
+    journal::record(&app, journal::FileOperation::Rename(file));
+    Ok(())
+}
+
+#[tauri::command]
+async fn undo_last_operation(app: tauri::AppHandle) -> Result<journal::JournalResult, String> {
+    journal::undo(&app).await
+}
+
+#[tauri::command]
+async fn redo_operation(app: tauri::AppHandle) -> Result<journal::JournalResult, String> {
+    journal::redo(&app).await
+}
+
+#[tauri::command]
+async fn get_media_metadata(app: tauri::AppHandle, payload: String) -> Result<probe::Probe, String> {
+    probe::probe(&app, &payload).await
+}
+
+#[tauri::command]
+async fn load_chapters(app: tauri::AppHandle, payload: String) -> Result<Vec<probe::Chapter>, String> {
+    chapters::load(&app, &payload).await
+}
+
+#[tauri::command]
+async fn list_subtitles(app: tauri::AppHandle, payload: String) -> Result<subtitles::SubtitleList, String> {
+    subtitles::list(&app, &payload).await
+}
+
+#[tauri::command]
+async fn load_subtitle(app: tauri::AppHandle, payload: subtitles::SubtitleRequest) -> Result<String, String> {
+    subtitles::load(&app, payload).await
+}
+
+#[tauri::command]
+fn select_subtitle(app: tauri::AppHandle, payload: subtitles::SubtitleRequest) -> Result<(), String> {
+    subtitles::select(&app, payload)
+}
+
+#[tauri::command]
+fn set_subtitle_offset(app: tauri::AppHandle, payload: subtitles::SubtitleOffset) -> Result<f64, String> {
+    subtitles::set_offset(&app, payload)
+}
+
+#[tauri::command]
+async fn export_subtitle(app: tauri::AppHandle, payload: subtitles::SubtitleExport) -> Result<(), String> {
+    subtitles::export(&app, payload).await
+}
+
+#[tauri::command]
+async fn resolve_convert_streams(app: tauri::AppHandle, payload: convert::ConvertStreamsRequest) -> Result<convert::ConvertStreams, String> {
+    convert::resolve(&app, payload).await
+}
+
+#[tauri::command]
+async fn extract_audio(app: tauri::AppHandle, payload: extract::AudioExtraction) -> Result<Vec<String>, String> {
+    extract::extract(&app, payload).await
+}
+
+#[tauri::command]
+async fn get_tags(app: tauri::AppHandle, payload: String) -> Result<std::collections::BTreeMap<String, String>, String> {
+    tags::read(&app, &payload).await
+}
+
+#[tauri::command]
+async fn write_tags(app: tauri::AppHandle, payload: tags::TagEdit) -> Result<probe::Probe, String> {
+    tags::write(&app, payload).await
+}
+
+#[tauri::command]
+async fn get_cover_art(app: tauri::AppHandle, payload: String) -> Result<Option<String>, String> {
+    cover::find(&app, &payload).await
+}
+
+#[tauri::command]
+async fn get_waveform(app: tauri::AppHandle, payload: waveform::WaveformRequest) -> Result<Option<waveform::Waveform>, String> {
+    waveform::get(&app, payload).await
+}
+
+#[tauri::command]
+async fn preview_tag_rename(app: tauri::AppHandle, payload: tag_rename::TagRenameOptions) -> Result<tag_rename::TagRenameReport, String> {
+    tag_rename::preview(&app, payload).await
+}
+
+#[tauri::command]
+fn apply_tag_rename(app: tauri::AppHandle, payload: Vec<tag_rename::TagRenameEntry>) -> tag_rename::TagRenameResult {
+    let keys = cache::file_keys(payload.iter().map(|entry| entry.old.as_str()));
+    let result = tag_rename::apply(payload);
+    if !result.renamed.is_empty() {
+        let files: Vec<journal::MovedFile> = result
+            .renamed
+            .iter()
+            .map(|entry| journal::MovedFile {
+                from: entry.old.clone(),
+                to: entry.new.clone(),
+                replaced: false,
+            })
+            .collect();
+        cache::rekey_moved(&app, &keys, &files);
+        journal::record(&app, journal::FileOperation::Move(files));
+    }
+    result
+}
+
+#[tauri::command]
+fn load_bookmarks(app: tauri::AppHandle, payload: String) -> Result<Vec<bookmarks::Bookmark>, String> {
+    bookmarks::load(&app, &payload)
+}
+
+#[tauri::command]
+fn get_bookmarks(app: tauri::AppHandle, payload: String) -> Result<Vec<bookmarks::Bookmark>, String> {
+    bookmarks::get(&app, &payload)
+}
+
+#[tauri::command]
+fn add_bookmark(app: tauri::AppHandle, payload: bookmarks::NewBookmark) -> Result<bookmarks::Bookmark, String> {
+    bookmarks::add(&app, payload)
+}
+
+#[tauri::command]
+fn update_bookmark(app: tauri::AppHandle, payload: bookmarks::BookmarkUpdate) -> Result<bookmarks::Bookmark, String> {
+    bookmarks::update(&app, payload)
+}
+
+#[tauri::command]
+fn remove_bookmark(app: tauri::AppHandle, payload: bookmarks::BookmarkRemoval) -> Result<(), String> {
+    bookmarks::remove(&app, payload)
+}
+
+#[tauri::command]
+fn export_bookmarks(app: tauri::AppHandle, payload: bookmarks::BookmarkTransfer) -> Result<(), String> {
+    bookmarks::export(&app, payload)
+}
+
+#[tauri::command]
+fn import_bookmarks(app: tauri::AppHandle, payload: bookmarks::BookmarkTransfer) -> Result<Vec<bookmarks::Bookmark>, String> {
+    bookmarks::import(&app, payload)
+}
+
+#[tauri::command]
+async fn get_silence(app: tauri::AppHandle, payload: silence::SilenceRequest) -> Result<Vec<silence::SilenceInterval>, String> {
+    silence::get(&app, payload).await
+}
+
+#[tauri::command]
+fn change_silence_mode(app: tauri::AppHandle, payload: String) -> Result<(), String> {
+    settings::update(&app, |settings| settings.silence.mode = payload).map(|_| ())
+}
+
+#[tauri::command]
+async fn get_replay_gain(app: tauri::AppHandle, payload: loudness::ReplayGainRequest) -> Result<loudness::ReplayGain, String> {
+    loudness::replay_gain(&app, payload).await
+}
+
+#[tauri::command]
+fn change_levelling_mode(app: tauri::AppHandle, payload: String) -> Result<(), String> {
+    settings::update(&app, |settings| settings.audio.levelling = payload).map(|_| ())
+}
+
+#[tauri::command]
+fn get_ab_loop(app: tauri::AppHandle, payload: String) -> Result<ab_loop::AbLoop, String> {
+    ab_loop::get(&app, &payload)
+}
+
+#[tauri::command]
+fn set_ab_loop(app: tauri::AppHandle, payload: ab_loop::AbLoopUpdate) -> Result<ab_loop::AbLoop, String> {
+    ab_loop::set(&app, payload)
+}
+
+#[tauri::command]
+async fn export_ab_loop(app: tauri::AppHandle, payload: ab_loop::AbLoopExport) -> Result<(), String> {
+    ab_loop::export(&app, payload).await
+}
+
+#[tauri::command]
+fn get_playlist(app: tauri::AppHandle) -> playlist::PlaylistSnapshot {
+    playlist::get(&app)
+}
+
+#[tauri::command]
+fn playlist_replace(app: tauri::AppHandle, payload: playlist::PlaylistReplace) -> playlist::PlaylistSnapshot {
+    playlist::replace(&app, payload)
+}
+
+/// None when playback stops at the end of the list
+#[tauri::command]
+fn playlist_next(app: tauri::AppHandle, payload: bool) -> Result<Option<playlist::PlaylistSnapshot>, String> {
+    let mut advanced = false;
+    let snapshot = playlist::change(&app, |playlist| {
+        advanced = playlist.next(payload).is_some();
+        Ok(())
+    })?;
+    Ok(advanced.then_some(snapshot))
+}
+
+#[tauri::command]
+fn playlist_previous(app: tauri::AppHandle) -> Result<Option<playlist::PlaylistSnapshot>, String> {
+    let mut moved = false;
+    let snapshot = playlist::change(&app, |playlist| {
+        moved = playlist.previous().is_some();
+        Ok(())
+    })?;
+    Ok(moved.then_some(snapshot))
+}
+
+#[tauri::command]
+fn playlist_jump(app: tauri::AppHandle, payload: usize) -> Result<playlist::PlaylistSnapshot, String> {
+    playlist::change(&app, |playlist| playlist.jump(payload))
+}
+
+#[tauri::command]
+fn playlist_insert_next(app: tauri::AppHandle, payload: Vec<playlist::PlaylistEntry>) -> Result<playlist::PlaylistSnapshot, String> {
+    playlist::change(&app, |playlist| {
+        playlist.insert_next(payload);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn playlist_set_shuffle(app: tauri::AppHandle, payload: playlist::ShuffleRequest) -> Result<playlist::PlaylistSnapshot, String> {
+    let snapshot = playlist::change(&app, |playlist| {
+        playlist.set_shuffle(payload.shuffle, payload.seed);
+        Ok(())
+    })?;
+    playlist::save_mode(&app, &snapshot)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+fn playlist_set_repeat(app: tauri::AppHandle, payload: playlist::RepeatMode) -> Result<playlist::PlaylistSnapshot, String> {
+    let snapshot = playlist::change(&app, |playlist| {
+        playlist.set_repeat(payload);
+        Ok(())
+    })?;
+    playlist::save_mode(&app, &snapshot)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+async fn detect_scenes(app: tauri::AppHandle, payload: scenes::SceneDetection) -> Result<Vec<scenes::SceneMarker>, String> {
+    scenes::detect(&app, payload).await
+}
+
+#[tauri::command]
+async fn save_scene_markers(app: tauri::AppHandle, payload: scenes::SceneSave) -> Result<(), String> {
+    scenes::save(&app, payload).await
 }
 
 #[tauri::command]
@@ -125,14 +407,28 @@ fn stat_all(payload: Vec<String>) -> Result<Vec<FileAttributeEx>, String> {
     Ok(result)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct MoveInfo {
-    from: Vec<String>,
-    to: String,
+#[tauri::command]
+async fn mv_all(app: tauri::AppHandle, payload: transfer::TransferInfo) -> Result<transfer::TransferResult, String> {
+    let result = transfer::mv_all(&app, payload).await?;
+    if !result.completed.is_empty() {
+        journal::record(&app, journal::FileOperation::Move(result.completed.clone()));
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn copy_all(app: tauri::AppHandle, payload: transfer::TransferInfo) -> Result<transfer::TransferResult, String> {
+    transfer::copy_all(&app, payload).await
+}
+
+#[tauri::command]
+fn cancel_transfer(app: tauri::AppHandle, payload: String) {
+    transfer::cancel(&app, &payload);
 }
+
 #[tauri::command]
-async fn mv_all(payload: MoveInfo) -> Result<(), String> {
-    zouni::fs::mv_all(&payload.from, payload.to)
+fn answer_transfer_conflict(app: tauri::AppHandle, payload: transfer::ConflictAnswerInfo) -> Result<(), String> {
+    transfer::answer_conflict(&app, payload)
 }
 
 #[tauri::command]
@@ -199,7 +495,61 @@ struct WriteFileInfo {
 
 #[tauri::command]
 fn write_text_file(payload: WriteFileInfo) -> Result<(), String> {
-    std::fs::write(payload.fullPath, payload.data).map_err(|e| e.to_string())
+    storage::write_atomic(Path::new(&payload.fullPath), payload.data.as_bytes())
+}
+
+#[tauri::command]
+fn get_settings(app: tauri::AppHandle) -> settings::Settings {
+    settings::get(&app)
+}
+
+#[tauri::command]
+fn update_settings(app: tauri::AppHandle, payload: serde_json::Value) -> Result<settings::Settings, String> {
+    let previous = settings::get(&app);
+    let settings = settings::update_from_json(&app, payload)?;
+    if previous.globalShortcuts != settings.globalShortcuts {
+        hotkeys::register(&app);
+    }
+    if previous.keymap != settings.keymap {
+        keymap::apply(&app)?;
+    } else if previous.locale != settings.locale || previous.video != settings.video {
+        menu::refresh(&app, &settings);
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+fn get_global_shortcut_status(app: tauri::AppHandle) -> hotkeys::ShortcutStatus {
+    hotkeys::status(&app)
+}
+
+#[tauri::command]
+fn validate_speed(payload: settings::CustomSpeed) -> Result<f64, String> {
+    payload.kind.validate(payload.value)
+}
+
+#[tauri::command]
+fn set_speed_presets(app: tauri::AppHandle, payload: settings::SpeedPresets) -> Result<settings::Settings, String> {
+    let settings = settings::set_speed_presets(&app, payload)?;
+    menu::refresh(&app, &settings);
+    Ok(settings)
+}
+
+#[tauri::command]
+fn change_locale(app: tauri::AppHandle, payload: settings::Locale) -> Result<String, String> {
+    let settings = settings::update(&app, |settings| settings.locale = payload)?;
+    menu::refresh(&app, &settings);
+    Ok(locale::Lang::from_settings(&settings).code().to_string())
+}
+
+#[tauri::command]
+fn get_keymap(app: tauri::AppHandle) -> keymap::Keymap {
+    keymap::get(&app)
+}
+
+#[tauri::command]
+fn get_settings_path(app: tauri::AppHandle) -> Result<String, String> {
+    settings::settings_path(&app).map(|path| path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
@@ -215,7 +565,7 @@ struct WriteAllFileInfo {
 }
 #[tauri::command]
 fn write_all(payload: WriteAllFileInfo) -> Result<(), String> {
-    std::fs::write(payload.fullPath, payload.data).map_err(|e| e.to_string())
+    storage::write_atomic(Path::new(&payload.fullPath), &payload.data)
 }
 
 #[tauri::command]
@@ -258,6 +608,12 @@ fn set_pause_thumbs(app: tauri::AppHandle, payload: tauri::ipc::Channel<String>)
     }
 }
 
+#[cfg(target_os = "linux")]
+#[tauri::command]
+async fn update_media_session(app: tauri::AppHandle, payload: mpris::MediaSession) -> Result<(), String> {
+    mpris::update(&app, payload).await
+}
+
 #[tauri::command]
 async fn spawn(app: tauri::AppHandle, payload: zouni::process::SpawnOption) -> Result<zouni::process::Output, zouni::process::Output> {
     shell::spawn(&app, payload).await
@@ -322,24 +678,15 @@ fn redo(window: WebviewWindow) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[allow(non_snake_case)]
-pub struct Settings {
-    pub theme: String,
-    pub fitToWindow: bool,
-    pub playbackSpeed: f64,
-    pub seekSpeed: f64,
-    pub groupBy: bool,
-    pub order: String,
-}
 #[tauri::command]
-fn prepare_windows(app: tauri::AppHandle, payload: Settings) -> tauri::Result<bool> {
+fn prepare_windows(app: tauri::AppHandle) -> tauri::Result<bool> {
+    let settings = settings::get(&app);
     let player = app.get_webview_window(PLAYER).unwrap();
     let playlist = app.get_webview_window(PLAY_LIST).unwrap();
     let player_window_handle = get_window_handle(&player);
     let list_window_handle = get_window_handle(&playlist);
 
-    let theme = match payload.theme.as_str() {
+    let theme = match settings.theme.as_str() {
         "dark" => tauri::Theme::Dark,
         "light" => tauri::Theme::Light,
         _ => tauri::Theme::Dark,
@@ -347,7 +694,7 @@ fn prepare_windows(app: tauri::AppHandle, payload: Settings) -> tauri::Result<bo
 
     player.set_theme(Some(theme))?;
 
-    menu::create(&app, player_window_handle, list_window_handle, &payload);
+    menu::create(&app, player_window_handle, list_window_handle, &settings);
 
     Ok(true)
 }
@@ -356,6 +703,19 @@ pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
             helper::setup(app);
+            settings::setup(app);
+            journal::setup(app);
+            bookmarks::setup(app);
+            chapters::setup(app);
+            loudness::setup(app);
+            ab_loop::setup(app);
+            playlist::setup(app);
+            subtitles::setup(app);
+            transfer::setup(app);
+            hotkeys::setup(app)?;
+            // Registers the hotkeys again once connected, so they must be set up first
+            #[cfg(target_os = "linux")]
+            mpris::setup(app);
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -378,12 +738,57 @@ pub fn run() {
             open_context_menu,
             open_list_context_menu,
             open_sort_context_menu,
+            update_menu,
             reveal,
             trash,
             exists,
             rename,
+            undo_last_operation,
+            redo_operation,
+            get_media_metadata,
+            resolve_convert_streams,
+            extract_audio,
+            get_tags,
+            get_cover_art,
+            get_waveform,
+            write_tags,
+            preview_tag_rename,
+            apply_tag_rename,
+            load_chapters,
+            list_subtitles,
+            load_subtitle,
+            select_subtitle,
+            set_subtitle_offset,
+            export_subtitle,
+            load_bookmarks,
+            get_bookmarks,
+            add_bookmark,
+            update_bookmark,
+            remove_bookmark,
+            export_bookmarks,
+            import_bookmarks,
+            get_silence,
+            change_silence_mode,
+            get_replay_gain,
+            change_levelling_mode,
+            get_ab_loop,
+            set_ab_loop,
+            export_ab_loop,
+            get_playlist,
+            playlist_replace,
+            playlist_next,
+            playlist_previous,
+            playlist_jump,
+            playlist_insert_next,
+            playlist_set_shuffle,
+            playlist_set_repeat,
+            detect_scenes,
+            save_scene_markers,
             stat,
             mv_all,
+            copy_all,
+            cancel_transfer,
+            answer_transfer_conflict,
             is_uris_available,
             read_uris,
             read_text,
@@ -394,11 +799,21 @@ pub fn run() {
             create,
             read_text_file,
             write_text_file,
+            get_settings,
+            update_settings,
+            get_global_shortcut_status,
+            get_keymap,
+            change_locale,
+            validate_speed,
+            set_speed_presets,
+            get_settings_path,
             remove,
             write_all,
             stat_all,
             set_play_thumbs,
             set_pause_thumbs,
+            #[cfg(target_os = "linux")]
+            update_media_session,
             message,
             open,
             save,
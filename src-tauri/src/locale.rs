@@ -0,0 +1,270 @@
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Ja];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ja => "ja",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Lang::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// The language chosen in settings, or the one the renderer detected when following the system
+    pub fn from_settings(settings: &Settings) -> Self {
+        Lang::from_code(&settings.locale.mode).or_else(|| Lang::from_code(&settings.locale.lang)).unwrap_or(Lang::En)
+    }
+}
+
+/// Every string shown by the native menus and thumbnail buttons
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub enum Label {
+    PlaybackSpeed,
+    SeekSpeed,
+    FitToWindow,
+    Playlist,
+    ToggleFullscreen,
+    PictureInPicture,
+    Capture,
+    Theme,
+    Dark,
+    Light,
+    Custom,
+    CustomSpeed,
+    Subtitles,
+    Off,
+    DelaySubtitles,
+    AdvanceSubtitles,
+    ResetSubtitleOffset,
+    SyncSubtitles,
+    ExportSubtitles,
+    Chapters,
+    NextChapter,
+    PreviousChapter,
+    Bookmarks,
+    Scene,
+    AddBookmark,
+    EditBookmarks,
+    ImportBookmarks,
+    ExportBookmarks,
+    DetectScenes,
+    AbLoop,
+    SetLoopStart,
+    SetLoopEnd,
+    ClearLoop,
+    ExportLoop,
+    Silence,
+    SkipSilence,
+    SpeedUpSilence,
+    Levelling,
+    LevelTracks,
+    LevelAlbums,
+    Lang,
+    System,
+    English,
+    Japanese,
+    Remove,
+    Trash,
+    CopyFileName,
+    CopyFullpath,
+    Reveal,
+    Rename,
+    Metadata,
+    EditTags,
+    RenameFromTags,
+    Convert,
+    Move,
+    Copy,
+    Undo,
+    Redo,
+    RemoveAll,
+    PlayNext,
+    Repeat,
+    RepeatOne,
+    RepeatAll,
+    Sort,
+    GroupBy,
+    NameAsc,
+    NameDesc,
+    DateAsc,
+    DateDesc,
+    Previous,
+    Play,
+    Pause,
+    Next,
+}
+
+/// Both tables are exhaustive matches, so a label without a translation fails to compile
+pub fn label(lang: Lang, label: Label) -> &'static str {
+    match lang {
+        Lang::En => en(label),
+        Lang::Ja => ja(label),
+    }
+}
+
+fn en(label: Label) -> &'static str {
+    match label {
+        Label::PlaybackSpeed => "Playback Speed",
+        Label::SeekSpeed => "Seek Speed",
+        Label::FitToWindow => "Fit To Window Size",
+        Label::Playlist => "Playlist",
+        Label::ToggleFullscreen => "Toggle Fullscreen",
+        Label::PictureInPicture => "Picture In Picture",
+        Label::Capture => "Capture",
+        Label::Theme => "Theme",
+        Label::Dark => "Dark",
+        Label::Light => "Light",
+        Label::Custom => "Custom",
+        Label::CustomSpeed => "Custom…",
+        Label::Subtitles => "Subtitles",
+        Label::Off => "Off",
+        Label::DelaySubtitles => "Delay Subtitles",
+        Label::AdvanceSubtitles => "Advance Subtitles",
+        Label::ResetSubtitleOffset => "Reset Subtitle Timing",
+        Label::SyncSubtitles => "Sync Subtitles by Two Points…",
+        Label::ExportSubtitles => "Export Synced Subtitles…",
+        Label::Chapters => "Chapters",
+        Label::NextChapter => "Next Chapter",
+        Label::PreviousChapter => "Previous Chapter",
+        Label::Bookmarks => "Bookmarks",
+        Label::Scene => "Scene",
+        Label::AddBookmark => "Add Bookmark",
+        Label::EditBookmarks => "Edit Bookmarks…",
+        Label::ImportBookmarks => "Import Bookmarks…",
+        Label::ExportBookmarks => "Export Bookmarks…",
+        Label::DetectScenes => "Detect Scenes…",
+        Label::AbLoop => "A-B Loop",
+        Label::SetLoopStart => "Set Loop Start (A)",
+        Label::SetLoopEnd => "Set Loop End (B)",
+        Label::ClearLoop => "Clear Loop",
+        Label::ExportLoop => "Export A-B Clip…",
+        Label::Silence => "Silence",
+        Label::SkipSilence => "Skip",
+        Label::SpeedUpSilence => "Play Faster",
+        Label::Levelling => "Volume Levelling",
+        Label::LevelTracks => "Per File",
+        Label::LevelAlbums => "Per Folder",
+        Label::Lang => "Language",
+        Label::System => "System",
+        Label::English => "English",
+        Label::Japanese => "日本語",
+        Label::Remove => "Remove",
+        Label::Trash => "Trash",
+        Label::CopyFileName => "Copy Name",
+        Label::CopyFullpath => "Copy Full Path",
+        Label::Reveal => "Reveal in File Explorer",
+        Label::Rename => "Rename",
+        Label::Metadata => "View Metadata",
+        Label::EditTags => "Edit Tags",
+        Label::RenameFromTags => "Rename from Tags…",
+        Label::Convert => "Convert",
+        Label::Move => "Move File",
+        Label::Copy => "Copy File",
+        Label::Undo => "Undo",
+        Label::Redo => "Redo",
+        Label::RemoveAll => "Clear Playlist",
+        Label::PlayNext => "Play Next",
+        Label::Repeat => "Repeat",
+        Label::RepeatOne => "One",
+        Label::RepeatAll => "All",
+        Label::Sort => "Sort",
+        Label::GroupBy => "Group By Directory",
+        Label::NameAsc => "Name(Asc)",
+        Label::NameDesc => "Name(Desc)",
+        Label::DateAsc => "Date(Asc)",
+        Label::DateDesc => "Date(Desc)",
+        Label::Previous => "Previous",
+        Label::Play => "Play",
+        Label::Pause => "Pause",
+        Label::Next => "Next",
+    }
+}
+
+fn ja(label: Label) -> &'static str {
+    match label {
+        Label::PlaybackSpeed => "再生速度",
+        Label::SeekSpeed => "シーク速度",
+        Label::FitToWindow => "ウィンドウサイズに合わせる",
+        Label::Playlist => "プレイリスト",
+        Label::ToggleFullscreen => "フルスクリーン",
+        Label::PictureInPicture => "ピクチャインピクチャ",
+        Label::Capture => "キャプチャ",
+        Label::Theme => "テーマ",
+        Label::Dark => "ダーク",
+        Label::Light => "ライト",
+        Label::Custom => "カスタム",
+        Label::CustomSpeed => "カスタム…",
+        Label::Subtitles => "字幕",
+        Label::Off => "オフ",
+        Label::DelaySubtitles => "字幕を遅らせる",
+        Label::AdvanceSubtitles => "字幕を早める",
+        Label::ResetSubtitleOffset => "字幕のタイミングをリセット",
+        Label::SyncSubtitles => "2点で字幕を同期…",
+        Label::ExportSubtitles => "同期した字幕を書き出す…",
+        Label::Chapters => "チャプター",
+        Label::NextChapter => "次のチャプター",
+        Label::PreviousChapter => "前のチャプター",
+        Label::Bookmarks => "ブックマーク",
+        Label::Scene => "シーン",
+        Label::AddBookmark => "ブックマークを追加",
+        Label::EditBookmarks => "ブックマークを編集…",
+        Label::ImportBookmarks => "ブックマークを読み込む…",
+        Label::ExportBookmarks => "ブックマークを書き出す…",
+        Label::DetectScenes => "シーンを検出…",
+        Label::AbLoop => "A-Bリピート",
+        Label::SetLoopStart => "開始点(A)を設定",
+        Label::SetLoopEnd => "終了点(B)を設定",
+        Label::ClearLoop => "リピートを解除",
+        Label::ExportLoop => "A-B区間をクリップとして書き出す…",
+        Label::Silence => "無音部分",
+        Label::SkipSilence => "スキップ",
+        Label::SpeedUpSilence => "早送り",
+        Label::Levelling => "音量の均一化",
+        Label::LevelTracks => "ファイルごと",
+        Label::LevelAlbums => "フォルダごと",
+        Label::Lang => "言語",
+        Label::System => "システム",
+        Label::English => "English",
+        Label::Japanese => "日本語",
+        Label::Remove => "削除",
+        Label::Trash => "ゴミ箱",
+        Label::CopyFileName => "ファイル名をコピー",
+        Label::CopyFullpath => "パスをコピー",
+        Label::Reveal => "エクスプローラーで開く",
+        Label::Rename => "名前の変更",
+        Label::Metadata => "メタデータを表示",
+        Label::EditTags => "タグを編集",
+        Label::RenameFromTags => "タグから名前を変更…",
+        Label::Convert => "コンバート",
+        Label::Move => "ファイルを移動",
+        Label::Copy => "ファイルをコピー",
+        Label::Undo => "元に戻す",
+        Label::Redo => "やり直し",
+        Label::RemoveAll => "プレイリストをクリア",
+        Label::PlayNext => "次に再生",
+        Label::Repeat => "リピート",
+        Label::RepeatOne => "1曲",
+        Label::RepeatAll => "すべて",
+        Label::Sort => "並べ替え",
+        Label::GroupBy => "フォルダ名でまとめる",
+        Label::NameAsc => "名前（昇順）",
+        Label::NameDesc => "名前（降順）",
+        Label::DateAsc => "日付（昇順）",
+        Label::DateDesc => "日付（降順）",
+        Label::Previous => "前へ",
+        Label::Play => "再生",
+        Label::Pause => "一時停止",
+        Label::Next => "次へ",
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{cache, convert, probe, shell};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tauri::{AppHandle, Manager};
+
+const LOUDNESS_CACHE: &str = "loudness";
+/// ReplayGain 2.0 reference level in LUFS
+const REFERENCE_LOUDNESS: f64 = -18.0;
+/// dB. Keeps near silent files from being boosted into noise
+const MAX_GAIN: f64 = 12.0;
+/// Directories with more files are not treated as albums
+const MAX_ALBUM_FILES: usize = 100;
+/// dBFS
+const MIN_PEAK: f64 = -120.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Loudness {
+    /// LUFS
+    integrated: f64,
+    /// dBFS
+    peak: f64,
+    duration: f64,
+}
+
+/// Directories whose files are being measured in the background
+#[derive(Default)]
+pub struct LoudnessJobs {
+    directories: HashSet<PathBuf>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayGainRequest {
+    pub filePath: String,
+    /// Uses one gain for all files of the same type in the directory
+    pub album: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayGain {
+    /// dB to apply to the file
+    pub gain: f64,
+    /// False when the album gain was asked for but the other files are still being measured
+    pub album: bool,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Mutex::new(LoudnessJobs::default()));
+}
+
+/// Gain that brings the file, or its album, to the reference loudness without clipping its peak
+pub async fn replay_gain(app: &AppHandle, request: ReplayGainRequest) -> Result<ReplayGain, String> {
+    let track = measure(app, &request.filePath).await?.ok_or_else(|| String::from("The file has no audio stream"))?;
+
+    if request.album {
+        let files = album_files(&request.filePath);
+        if files.len() > 1 {
+            let measured: Option<Vec<Option<Loudness>>> = files.iter().map(|file| cached(app, file)).collect();
+            match measured {
+                Some(measured) => {
+                    let album = combine(&measured.into_iter().flatten().collect::<Vec<_>>());
+                    return Ok(ReplayGain {
+                        gain: gain(&album),
+                        album: true,
+                    });
+                }
+                None => measure_in_background(app, &request.filePath, files),
+            }
+        }
+    }
+
+    Ok(ReplayGain {
+        gain: gain(&track),
+        album: false,
+    })
+}
+
+fn gain(loudness: &Loudness) -> f64 {
+    (REFERENCE_LOUDNESS - loudness.integrated).min(-loudness.peak).min(MAX_GAIN)
+}
+
+/// Duration weighted energy average of the loudness, and the highest peak
+fn combine(tracks: &[Loudness]) -> Loudness {
+    let duration: f64 = tracks.iter().map(|track| track.duration).sum();
+    let energy: f64 = tracks.iter().map(|track| track.duration * 10f64.powf(track.integrated / 10.0)).sum::<f64>() / duration.max(f64::EPSILON);
+
+    Loudness {
+        integrated: 10.0 * energy.log10(),
+        peak: tracks.iter().map(|track| track.peak).fold(f64::NEG_INFINITY, f64::max),
+        duration,
+    }
+}
+
+/// Files of the same type in the same directory
+fn album_files(file_path: &str) -> Vec<String> {
+    let path = Path::new(file_path);
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.is_file() && file.extension().map(|extension| extension.to_string_lossy().to_lowercase()) == extension)
+        .map(|file| file.to_string_lossy().to_string())
+        .collect();
+
+    if files.len() > MAX_ALBUM_FILES {
+        return Vec::new();
+    }
+
+    files.sort();
+    files
+}
+
+fn measure_in_background(app: &AppHandle, file_path: &str, files: Vec<String>) {
+    let Some(dir) = Path::new(file_path).parent().map(Path::to_path_buf) else {
+        return;
+    };
+
+    if !app.state::<Mutex<LoudnessJobs>>().lock().unwrap().directories.insert(dir.clone()) {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for file in files {
+            // Files that cannot be measured are left out of the album instead of being retried on every load
+            if measure(&app, &file).await.is_err() {
+                let _ = cache::write(&app, LOUDNESS_CACHE, &file, "json", b"null");
+            }
+        }
+        app.state::<Mutex<LoudnessJobs>>().lock().unwrap().directories.remove(&dir);
+    });
+}
+
+/// None inside means the file was measured and has no audio
+fn cached(app: &AppHandle, file_path: &str) -> Option<Option<Loudness>> {
+    cache::read(app, LOUDNESS_CACHE, file_path, "json").and_then(|data| serde_json::from_slice(&data).ok())
+}
+
+async fn measure(app: &AppHandle, file_path: &str) -> Result<Option<Loudness>, String> {
+    if let Some(loudness) = cached(app, file_path) {
+        return Ok(loudness);
+    }
+
+    let probe = probe::probe(app, file_path).await?;
+    let loudness = match convert::select(&probe, "audio", None)? {
+        Some(stream) => {
+            let map = format!("0:{}", stream.index);
+            // Per frame values are logged at verbose level, leaving only the summary at info level
+            let args = ["-hide_banner", "-nostats", "-i", file_path, "-map", &map, "-vn", "-sn", "-dn", "-af", "ebur128=peak=true:framelog=verbose", "-f", "null", "-"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect();
+            let output = shell::exec(app, "ffmpeg", args).await?;
+            let duration = probe.format.duration.as_ref().and_then(|duration| duration.parse::<f64>().ok()).unwrap_or_default();
+            Some(parse_summary(&output.stderr, duration).ok_or_else(|| String::from("Failed to measure the loudness"))?)
+        }
+        None => None,
+    };
+
+    let data = serde_json::to_vec(&loudness).map_err(|e| e.to_string())?;
+    cache::write(app, LOUDNESS_CACHE, file_path, "json", &data)?;
+
+    Ok(loudness)
+}
+
+/// Reads "I: -19.2 LUFS" and "Peak: -0.6 dBFS" from the summary ebur128 prints at the end
+fn parse_summary(log: &str, duration: f64) -> Option<Loudness> {
+    let (_, summary) = log.rsplit_once("Summary:")?;
+
+    let value = |key: &str| summary.lines().map(str::trim).find_map(|line| line.strip_prefix(key)).and_then(|rest| rest.split_whitespace().next()).and_then(|value| value.parse::<f64>().ok());
+
+    Some(Loudness {
+        integrated: value("I:")?,
+        // The peak is missing when ffmpeg was built without true peak support, and -inf for digital silence which JSON cannot hold
+        peak: value("Peak:").unwrap_or(0.0).max(MIN_PEAK),
+        duration,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "[Parsed_ebur128_0 @ 0x1] t: 9.9     TARGET:-23 LUFS    M: -20.1 S: -19.8     I: -20.5 LUFS       LRA:   3.1 LU  FTPK:  -2.0 dBFS  TPK:  -1.1 dBFS
+[Parsed_ebur128_0 @ 0x1] Summary:
+
+  Integrated loudness:
+    I:         -19.2 LUFS
+    Threshold: -29.6 LUFS
+
+  Loudness range:
+    LRA:         4.3 LU
+    Threshold: -49.6 LUFS
+    LRA low:   -22.1 LUFS
+    LRA high:  -17.8 LUFS
+
+  True peak:
+    Peak:       -0.6 dBFS
+";
+
+    #[test]
+    fn parse_summary_reads_integrated_and_peak() {
+        let loudness = parse_summary(LOG, 12.5).unwrap();
+        assert_eq!(loudness.integrated, -19.2);
+        assert_eq!(loudness.peak, -0.6);
+        assert_eq!(loudness.duration, 12.5);
+    }
+
+    #[test]
+    fn parse_summary_defaults_missing_peak() {
+        let log = LOG.replace("    Peak:       -0.6 dBFS\n", "");
+        assert_eq!(parse_summary(&log, 1.0).unwrap().peak, 0.0);
+    }
+
+    #[test]
+    fn parse_summary_clamps_silent_peak() {
+        let log = LOG.replace("-0.6 dBFS", "-inf dBFS");
+        assert_eq!(parse_summary(&log, 1.0).unwrap().peak, MIN_PEAK);
+    }
+
+    #[test]
+    fn parse_summary_requires_summary_and_integrated() {
+        assert!(parse_summary(LOG.split("Summary:").next().unwrap(), 1.0).is_none());
+        assert!(parse_summary(&LOG.replace("-19.2 LUFS", "nan? LUFS"), 1.0).is_none());
+    }
+}
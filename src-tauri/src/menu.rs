@@ -1,6 +1,11 @@
-use crate::Settings;
+use crate::{
+    keymap::{self, Keymap},
+    locale::{self, Label, Lang},
+    settings::Settings,
+    subtitles,
+};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use strum_macros::Display;
 use tauri::async_runtime::Mutex;
 #[cfg(target_os = "windows")]
@@ -8,20 +13,44 @@ use tauri::path::BaseDirectory;
 use tauri::{Emitter, EventTarget, Manager};
 use wcpopup::{
     config::{ColorScheme, Config, MenuSize, Theme, ThemeColor, DEFAULT_DARK_COLOR_SCHEME},
-    Menu, MenuBuilder,
+    Menu, MenuBuilder, MenuItemType,
 };
 #[cfg(target_os = "windows")]
 use zouni::ThumbButton;
 
-pub struct Menus(HashMap<String, Menu>);
-type MenusState = Mutex<Menus>;
+/// Item state changed at runtime, kept so that a rebuilt menu looks the same as the one it replaces
+#[derive(Default)]
+struct MenuState {
+    disabled: HashSet<String>,
+    checked: HashMap<String, bool>,
+    selected: HashMap<String, String>,
+    /// Items of submenus whose contents depend on the current file, as (id, label)
+    entries: HashMap<String, Vec<(String, String)>>,
+}
+
+pub struct Menus {
+    menus: HashMap<String, Menu>,
+    states: HashMap<String, MenuState>,
+    player_window_handle: isize,
+    list_window_handle: isize,
+}
+
+/// A change to the menus that may have to wait for an open popup
+type MenuChange = Box<dyn FnOnce(&mut Menus) + Send>;
+
+pub struct MenusState {
+    menus: Mutex<Menus>,
+    /// Changes made while a popup held the menus, applied once it closes
+    pending: std::sync::Mutex<Vec<MenuChange>>,
+}
 
 const MENU_EVENT_NAME: &str = "contextmenu-event";
 pub const PLAYER: &str = "Player";
 pub const PLAY_LIST: &str = "Playlist";
 pub const SORT_MENU_NAME: &str = "Sort";
-const PLAYBACK_SPEEDS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
-const SEEK_SPEEDS: [f64; 9] = [0.03, 0.05, 0.1, 0.5, 1.0, 3.0, 5.0, 10.0, 20.0];
+/// Default presets for the speed submenus
+pub const PLAYBACK_SPEEDS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+pub const SEEK_SPEEDS: [f64; 9] = [0.03, 0.05, 0.1, 0.5, 1.0, 3.0, 5.0, 10.0, 20.0];
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Position {
@@ -29,6 +58,53 @@ pub struct Position {
     y: i32,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct MenuItemUpdate {
+    id: String,
+    disabled: Option<bool>,
+    checked: Option<bool>,
+    /// Radio group name. A checked radio item becomes the selected item of its group.
+    group: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MenuUpdate {
+    menu: String,
+    items: Vec<MenuItemUpdate>,
+}
+
+struct MenuContext<'a> {
+    settings: &'a Settings,
+    lang: Lang,
+    keymap: Keymap,
+    state: Option<&'a MenuState>,
+}
+
+impl MenuContext<'_> {
+    fn label(&self, label: Label) -> &'static str {
+        locale::label(self.lang, label)
+    }
+
+    fn disabled(&self, id: &str) -> bool {
+        self.state.is_some_and(|state| state.disabled.contains(id))
+    }
+
+    fn checked(&self, id: &str, default: bool) -> bool {
+        self.state.and_then(|state| state.checked.get(id)).copied().unwrap_or(default)
+    }
+
+    fn entries(&self, submenu: &str) -> &[(String, String)] {
+        self.state.and_then(|state| state.entries.get(submenu)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn selected(&self, group: &str, id: &str, default: bool) -> bool {
+        match self.state.and_then(|state| state.selected.get(group)) {
+            Some(selected) => selected == id,
+            None => default,
+        }
+    }
+}
+
 #[derive(Clone, Display)]
 pub enum PlayerMenu {
     PlaybackSpeed,
@@ -37,8 +113,31 @@ pub enum PlayerMenu {
     FitToWindow,
     ToggleFullscreen,
     Theme,
+    Lang,
     Capture,
     PictureInPicture,
+    Bookmarks,
+    AddBookmark,
+    EditBookmarks,
+    ImportBookmarks,
+    ExportBookmarks,
+    DetectScenes,
+    Silence,
+    Levelling,
+    AbLoop,
+    SetLoopStart,
+    SetLoopEnd,
+    ClearLoop,
+    ExportLoop,
+    Chapters,
+    NextChapter,
+    PreviousChapter,
+    Subtitles,
+    DelaySubtitles,
+    AdvanceSubtitles,
+    ResetSubtitleOffset,
+    SyncSubtitles,
+    ExportSubtitles,
 }
 
 #[derive(Clone, Display)]
@@ -50,10 +149,17 @@ pub enum PlaylistMenu {
     CopyFullpath,
     Reveal,
     Metadata,
+    EditTags,
     Convert,
     Sort,
     Rename,
+    RenameFromTags,
     Move,
+    Copy,
+    Undo,
+    Redo,
+    PlayNext,
+    Repeat,
 }
 
 #[derive(Clone, Display)]
@@ -65,13 +171,105 @@ pub enum SortMenu {
     DateDesc,
 }
 
+/// Menu ids and the label each one is shown with
+trait MenuItemId: std::fmt::Display {
+    fn label(&self) -> Label;
+}
+
+impl MenuItemId for PlayerMenu {
+    fn label(&self) -> Label {
+        match self {
+            PlayerMenu::PlaybackSpeed => Label::PlaybackSpeed,
+            PlayerMenu::SeekSpeed => Label::SeekSpeed,
+            PlayerMenu::TogglePlaylistWindow => Label::Playlist,
+            PlayerMenu::FitToWindow => Label::FitToWindow,
+            PlayerMenu::ToggleFullscreen => Label::ToggleFullscreen,
+            PlayerMenu::Theme => Label::Theme,
+            PlayerMenu::Lang => Label::Lang,
+            PlayerMenu::Capture => Label::Capture,
+            PlayerMenu::PictureInPicture => Label::PictureInPicture,
+            PlayerMenu::Bookmarks => Label::Bookmarks,
+            PlayerMenu::AddBookmark => Label::AddBookmark,
+            PlayerMenu::EditBookmarks => Label::EditBookmarks,
+            PlayerMenu::ImportBookmarks => Label::ImportBookmarks,
+            PlayerMenu::ExportBookmarks => Label::ExportBookmarks,
+            PlayerMenu::DetectScenes => Label::DetectScenes,
+            PlayerMenu::Silence => Label::Silence,
+            PlayerMenu::Levelling => Label::Levelling,
+            PlayerMenu::AbLoop => Label::AbLoop,
+            PlayerMenu::SetLoopStart => Label::SetLoopStart,
+            PlayerMenu::SetLoopEnd => Label::SetLoopEnd,
+            PlayerMenu::ClearLoop => Label::ClearLoop,
+            PlayerMenu::ExportLoop => Label::ExportLoop,
+            PlayerMenu::Chapters => Label::Chapters,
+            PlayerMenu::NextChapter => Label::NextChapter,
+            PlayerMenu::PreviousChapter => Label::PreviousChapter,
+            PlayerMenu::Subtitles => Label::Subtitles,
+            PlayerMenu::DelaySubtitles => Label::DelaySubtitles,
+            PlayerMenu::AdvanceSubtitles => Label::AdvanceSubtitles,
+            PlayerMenu::ResetSubtitleOffset => Label::ResetSubtitleOffset,
+            PlayerMenu::SyncSubtitles => Label::SyncSubtitles,
+            PlayerMenu::ExportSubtitles => Label::ExportSubtitles,
+        }
+    }
+}
+
+impl MenuItemId for PlaylistMenu {
+    fn label(&self) -> Label {
+        match self {
+            PlaylistMenu::Remove => Label::Remove,
+            PlaylistMenu::RemoveAll => Label::RemoveAll,
+            PlaylistMenu::Trash => Label::Trash,
+            PlaylistMenu::CopyFileName => Label::CopyFileName,
+            PlaylistMenu::CopyFullpath => Label::CopyFullpath,
+            PlaylistMenu::Reveal => Label::Reveal,
+            PlaylistMenu::Metadata => Label::Metadata,
+            PlaylistMenu::EditTags => Label::EditTags,
+            PlaylistMenu::Convert => Label::Convert,
+            PlaylistMenu::Sort => Label::Sort,
+            PlaylistMenu::Rename => Label::Rename,
+            PlaylistMenu::RenameFromTags => Label::RenameFromTags,
+            PlaylistMenu::Move => Label::Move,
+            PlaylistMenu::Copy => Label::Copy,
+            PlaylistMenu::Undo => Label::Undo,
+            PlaylistMenu::Redo => Label::Redo,
+            PlaylistMenu::PlayNext => Label::PlayNext,
+            PlaylistMenu::Repeat => Label::Repeat,
+        }
+    }
+}
+
+impl MenuItemId for SortMenu {
+    fn label(&self) -> Label {
+        match self {
+            SortMenu::GroupBy => Label::GroupBy,
+            SortMenu::NameAsc => Label::NameAsc,
+            SortMenu::NameDesc => Label::NameDesc,
+            SortMenu::DateAsc => Label::DateAsc,
+            SortMenu::DateDesc => Label::DateDesc,
+        }
+    }
+}
+
 pub async fn popup_menu(app_handle: &tauri::AppHandle, window_label: &str, menu_name: &str, position: Position) {
     let state = app_handle.state::<MenusState>();
-    let menus = state.try_lock().unwrap();
-    let menu = menus.0.get(menu_name).unwrap();
+    let mut menus = state.menus.lock().await;
+    let menu = menus.menus.get(menu_name).unwrap();
     let result = menu.popup_at_async(position.x, position.y).await;
 
     if let Some(item) = result {
+        // The menu has already toggled the clicked item, so only the stored state needs to follow
+        let menu_state = menus.states.entry(menu_name.to_string()).or_default();
+        match item.menu_item_type {
+            MenuItemType::Checkbox => {
+                menu_state.checked.insert(item.id.clone(), item.checked);
+            }
+            MenuItemType::Radio => {
+                menu_state.selected.insert(item.name.clone(), item.id.clone());
+            }
+            _ => {}
+        }
+
         app_handle
             .emit_to(
                 EventTarget::WebviewWindow {
@@ -82,17 +280,50 @@ pub async fn popup_menu(app_handle: &tauri::AppHandle, window_label: &str, menu_
             )
             .unwrap();
     };
+
+    drop(menus);
+
+    // Changes made while the popup was open build menus, which belongs on the main thread
+    let app = app_handle.clone();
+    let _ = app_handle.run_on_main_thread(move || apply_pending(&app.state::<MenusState>()));
 }
 
-pub fn change_menu_theme(app_handle: &tauri::AppHandle, theme: Theme) {
-    let state = app_handle.state::<MenusState>();
-    let menus = state.try_lock().unwrap();
+/// Applies the change now, or queues it until the open popup releases the menus
+fn change_menus<F: FnOnce(&mut Menus) + Send + 'static>(app_handle: &tauri::AppHandle, change: F) {
+    let Some(state) = app_handle.try_state::<MenusState>() else {
+        return;
+    };
+
+    state.pending.lock().unwrap().push(Box::new(change));
+    apply_pending(&state);
+}
 
-    for menu in menus.0.values() {
-        menu.set_theme(theme);
+/// Runs the queued changes. When the menus are held, the holder runs them on release instead.
+fn apply_pending(state: &MenusState) {
+    loop {
+        let Ok(mut menus) = state.menus.try_lock() else {
+            return;
+        };
+
+        let changes = std::mem::take(&mut *state.pending.lock().unwrap());
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes {
+            change(&mut menus);
+        }
     }
 }
 
+pub fn change_menu_theme(app_handle: &tauri::AppHandle, theme: Theme) {
+    change_menus(app_handle, move |menus| {
+        for menu in menus.menus.values() {
+            menu.set_theme(theme);
+        }
+    });
+}
+
 fn to_menu_theme(theme_str: &str) -> Theme {
     if theme_str == "dark" {
         Theme::Dark
@@ -122,101 +353,396 @@ fn get_menu_config(theme: &str) -> Config {
 }
 
 pub fn create(app_handle: &tauri::AppHandle, player_window_handle: isize, list_window_handle: isize, settings: &Settings) {
-    let player = create_player_menu(player_window_handle, settings);
-    let list = create_playlist_menu(list_window_handle, settings);
-    let sort = create_sort_menu(list_window_handle, settings);
-    let menus = Menus(HashMap::from([(PLAYER.to_string(), player), (PLAY_LIST.to_string(), list), (SORT_MENU_NAME.to_string(), sort)]));
-    app_handle.manage(Mutex::new(menus));
+    let mut menus = Menus {
+        menus: HashMap::new(),
+        states: HashMap::new(),
+        player_window_handle,
+        list_window_handle,
+    };
+    for name in [PLAYER, PLAY_LIST, SORT_MENU_NAME] {
+        let menu = build_menu(&menus, name, settings);
+        menus.menus.insert(name.to_string(), menu);
+    }
+    app_handle.manage(MenusState {
+        menus: Mutex::new(menus),
+        pending: std::sync::Mutex::new(Vec::new()),
+    });
+}
+
+/// Replaces every menu with one built from the given settings, after the open popup closes if there is one
+pub fn refresh(app_handle: &tauri::AppHandle, settings: &Settings) {
+    let settings = settings.clone();
+    change_menus(app_handle, move |menus| {
+        for name in [PLAYER, PLAY_LIST, SORT_MENU_NAME] {
+            let menu = build_menu(menus, name, &settings);
+            menus.menus.insert(name.to_string(), menu);
+        }
+    });
+}
+
+fn check_menu_name(menu_name: &str) -> Result<(), String> {
+    if [PLAYER, PLAY_LIST, SORT_MENU_NAME].contains(&menu_name) {
+        Ok(())
+    } else {
+        Err(format!("Unknown menu: {}", menu_name))
+    }
+}
+
+/// Enables, disables and checks items in place. Radio selections rebuild the menu since radio ids are only unique within a group.
+pub fn update(app_handle: &tauri::AppHandle, update: MenuUpdate, settings: &Settings) -> Result<(), String> {
+    check_menu_name(&update.menu)?;
+
+    let settings = settings.clone();
+    change_menus(app_handle, move |menus| apply_update(menus, update, &settings));
+    Ok(())
+}
+
+fn apply_update(menus: &mut Menus, update: MenuUpdate, settings: &Settings) {
+    let Menus {
+        menus: built,
+        states,
+        ..
+    } = menus;
+
+    let Some(menu) = built.get(&update.menu) else {
+        return;
+    };
+    let menu_state = states.entry(update.menu.clone()).or_default();
+    let mut needs_rebuild = false;
+
+    for item in update.items {
+        if let Some(disabled) = item.disabled {
+            let changed = if disabled {
+                menu_state.disabled.insert(item.id.clone())
+            } else {
+                menu_state.disabled.remove(&item.id)
+            };
+            needs_rebuild |= changed && item.group.is_some();
+        }
+
+        match (&item.group, item.checked) {
+            (Some(group), Some(true)) if menu_state.selected.get(group) != Some(&item.id) => {
+                menu_state.selected.insert(group.clone(), item.id.clone());
+                needs_rebuild = true;
+            }
+            (None, Some(checked)) => {
+                menu_state.checked.insert(item.id.clone(), checked);
+            }
+            _ => {}
+        }
+
+        if item.group.is_none() {
+            if let Some(mut menu_item) = menu.get_menu_item_by_id(&item.id) {
+                if let Some(disabled) = item.disabled {
+                    menu_item.set_disabled(disabled);
+                }
+                if let Some(checked) = item.checked {
+                    menu_item.set_checked(checked);
+                }
+            }
+        }
+    }
+
+    if needs_rebuild {
+        let menu = build_menu(menus, &update.menu, settings);
+        menus.menus.insert(update.menu, menu);
+    }
+}
+
+/// Formats a position for menu labels as "m:ss" or "h:mm:ss"
+pub fn format_time(time: f64) -> String {
+    let seconds = time as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Replaces the items of a submenu, optionally with one of them checked, and rebuilds the menu that contains it
+pub fn set_entries(app_handle: &tauri::AppHandle, menu_name: &str, submenu: PlayerMenu, entries: Vec<(String, String)>, selected: Option<String>, settings: &Settings) {
+    let (menu_name, submenu, settings) = (menu_name.to_string(), submenu.to_string(), settings.clone());
+    change_menus(app_handle, move |menus| {
+        let menu_state = menus.states.entry(menu_name.clone()).or_default();
+        match selected {
+            Some(selected) => menu_state.selected.insert(submenu.clone(), selected),
+            None => menu_state.selected.remove(&submenu),
+        };
+        menu_state.entries.insert(submenu, entries);
+
+        let menu = build_menu(menus, &menu_name, &settings);
+        menus.menus.insert(menu_name, menu);
+    });
+}
+
+fn build_menu(menus: &Menus, menu_name: &str, settings: &Settings) -> Menu {
+    let context = MenuContext {
+        settings,
+        lang: Lang::from_settings(settings),
+        keymap: keymap::resolve(&settings.keymap),
+        state: menus.states.get(menu_name),
+    };
+
+    match menu_name {
+        PLAYER => create_player_menu(menus.player_window_handle, &context),
+        PLAY_LIST => create_playlist_menu(menus.list_window_handle, &context),
+        _ => create_sort_menu(menus.list_window_handle, &context),
+    }
+}
+
+fn text(builder: &mut MenuBuilder, context: &MenuContext, scope: &str, id: &str, label: &str) {
+    let disabled = context.disabled(id);
+    if let Some(accelerator) = keymap::accelerator(&context.keymap, scope, id) {
+        builder.text_with_accelerator(id, label, disabled, accelerator);
+    } else {
+        builder.text(id, label, disabled);
+    }
+}
+
+fn item<T: MenuItemId>(builder: &mut MenuBuilder, context: &MenuContext, scope: &str, id: T) {
+    text(builder, context, scope, &id.to_string(), context.label(id.label()));
 }
 
-fn create_player_menu(window_handle: isize, settings: &Settings) -> Menu {
+fn create_player_menu(window_handle: isize, context: &MenuContext) -> Menu {
+    let settings = context.settings;
     let config = get_menu_config(&settings.theme);
 
     let mut builder = MenuBuilder::new_from_config(window_handle, config);
 
-    create_playback_speed_submenu(&mut builder, settings);
-    create_seek_speed_submenu(&mut builder, settings);
-    builder.check(&PlayerMenu::FitToWindow.to_string(), "Fit To Window Size", settings.fitToWindow, false);
+    create_playback_speed_submenu(&mut builder, context);
+    create_seek_speed_submenu(&mut builder, context);
+    let fit_to_window = PlayerMenu::FitToWindow.to_string();
+    builder.check(&fit_to_window, context.label(Label::FitToWindow), context.checked(&fit_to_window, settings.video.fitToWindow), context.disabled(&fit_to_window));
     builder.separator();
-    builder.text_with_accelerator(&PlayerMenu::TogglePlaylistWindow.to_string(), "Playlist", false, "Ctrl+P");
-    builder.text_with_accelerator(&PlayerMenu::ToggleFullscreen.to_string(), "Toggle Fullscreen", false, "F11");
-    builder.text(&PlayerMenu::PictureInPicture.to_string(), "Picture In Picture", false);
+    item(&mut builder, context, PLAYER, PlayerMenu::TogglePlaylistWindow);
+    item(&mut builder, context, PLAYER, PlayerMenu::ToggleFullscreen);
+    item(&mut builder, context, PLAYER, PlayerMenu::PictureInPicture);
     builder.separator();
-    builder.text_with_accelerator(&PlayerMenu::Capture.to_string(), "Capture", false, "Ctrl+S");
+    item(&mut builder, context, PLAYER, PlayerMenu::Capture);
     builder.separator();
-    create_theme_submenu(&mut builder, settings);
+    create_subtitles_submenu(&mut builder, context);
+    create_chapters_submenu(&mut builder, context);
+    create_bookmarks_submenu(&mut builder, context);
+    create_ab_loop_submenu(&mut builder, context);
+    create_silence_submenu(&mut builder, context);
+    create_levelling_submenu(&mut builder, context);
+    builder.separator();
+    create_theme_submenu(&mut builder, context);
+    create_lang_submenu(&mut builder, context);
 
     builder.build().unwrap()
 }
 
-fn create_playback_speed_submenu(builder: &mut MenuBuilder, settings: &Settings) {
-    let id = PlayerMenu::PlaybackSpeed.to_string();
-    let mut parent = builder.submenu(&id, "Playback Speed", false);
+fn create_playback_speed_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let video = &context.settings.video;
+    create_speed_submenu(builder, context, PlayerMenu::PlaybackSpeed, Label::PlaybackSpeed, &video.playbackSpeedPresets, video.playbackSpeed);
+}
+
+fn create_seek_speed_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let video = &context.settings.video;
+    create_speed_submenu(builder, context, PlayerMenu::SeekSpeed, Label::SeekSpeed, &video.seekSpeedPresets, video.seekSpeed);
+}
+
+/// Lists the presets, plus a checked item for a current speed that is not one of them
+fn create_speed_submenu(builder: &mut MenuBuilder, context: &MenuContext, menu: PlayerMenu, label: Label, presets: &[f64], speed: f64) {
+    let id = menu.to_string();
+    let mut parent = builder.submenu(&id, context.label(label), context.disabled(&id));
 
-    for speed in PLAYBACK_SPEEDS {
-        let speed_str = &speed.to_string();
-        parent.radio(speed_str, speed_str, &id, speed == settings.playbackSpeed, false);
+    let current = context.state.and_then(|state| state.selected.get(&id)).and_then(|selected| selected.parse::<f64>().ok()).unwrap_or(speed);
+
+    for preset in presets {
+        let preset_str = &preset.to_string();
+        parent.radio(preset_str, preset_str, &id, *preset == current, false);
+    }
+
+    if !presets.contains(&current) {
+        let current_str = &current.to_string();
+        parent.radio(current_str, &format!("{} ({})", context.label(Label::Custom), current_str), &id, true, false);
     }
 
+    parent.separator();
+    parent.text(&format!("Custom{}", id), context.label(Label::CustomSpeed), false);
+
     parent.build().unwrap();
 }
 
-fn create_seek_speed_submenu(builder: &mut MenuBuilder, settings: &Settings) {
-    let id = PlayerMenu::SeekSpeed.to_string();
-    let mut parent = builder.submenu(&id, "Seek Speed", false);
+fn create_subtitles_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Subtitles.to_string();
+    let entries = context.entries(&id);
+    let mut parent = builder.submenu(&id, context.label(Label::Subtitles), context.disabled(&id) || entries.is_empty());
 
-    for speed in SEEK_SPEEDS {
-        let speed_str = &speed.to_string();
-        parent.radio(speed_str, speed_str, &id, speed == settings.seekSpeed, false);
+    parent.radio(subtitles::SUBTITLES_OFF, context.label(Label::Off), &id, context.selected(&id, subtitles::SUBTITLES_OFF, true), false);
+    for (entry_id, label) in entries {
+        parent.radio(entry_id, label, &id, context.selected(&id, entry_id, false), false);
     }
+    parent.separator();
+    item(&mut parent, context, PLAYER, PlayerMenu::DelaySubtitles);
+    item(&mut parent, context, PLAYER, PlayerMenu::AdvanceSubtitles);
+    item(&mut parent, context, PLAYER, PlayerMenu::ResetSubtitleOffset);
+    item(&mut parent, context, PLAYER, PlayerMenu::SyncSubtitles);
+    item(&mut parent, context, PLAYER, PlayerMenu::ExportSubtitles);
 
     parent.build().unwrap();
 }
 
-fn create_theme_submenu(builder: &mut MenuBuilder, settings: &Settings) {
+fn create_chapters_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Chapters.to_string();
+    let entries = context.entries(&id);
+    let mut parent = builder.submenu(&id, context.label(Label::Chapters), context.disabled(&id) || entries.is_empty());
+
+    item(&mut parent, context, PLAYER, PlayerMenu::NextChapter);
+    item(&mut parent, context, PLAYER, PlayerMenu::PreviousChapter);
+    parent.separator();
+    for (entry_id, label) in entries {
+        parent.radio(entry_id, label, &id, context.selected(&id, entry_id, false), false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_bookmarks_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Bookmarks.to_string();
+    let mut parent = builder.submenu(&id, context.label(Label::Bookmarks), context.disabled(&id));
+
+    item(&mut parent, context, PLAYER, PlayerMenu::AddBookmark);
+    item(&mut parent, context, PLAYER, PlayerMenu::EditBookmarks);
+    item(&mut parent, context, PLAYER, PlayerMenu::ImportBookmarks);
+    item(&mut parent, context, PLAYER, PlayerMenu::ExportBookmarks);
+    item(&mut parent, context, PLAYER, PlayerMenu::DetectScenes);
+
+    let entries = context.entries(&id);
+    if !entries.is_empty() {
+        parent.separator();
+    }
+    // Bookmarks are identified by their ids, the Player window looks up the time
+    for (entry_id, label) in entries {
+        parent.text(entry_id, label, false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_ab_loop_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::AbLoop.to_string();
+    let mut parent = builder.submenu(&id, context.label(Label::AbLoop), context.disabled(&id));
+
+    item(&mut parent, context, PLAYER, PlayerMenu::SetLoopStart);
+    item(&mut parent, context, PLAYER, PlayerMenu::SetLoopEnd);
+    item(&mut parent, context, PLAYER, PlayerMenu::ClearLoop);
+    parent.separator();
+    item(&mut parent, context, PLAYER, PlayerMenu::ExportLoop);
+
+    parent.build().unwrap();
+}
+
+fn create_silence_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Silence.to_string();
+    let mode = &context.settings.silence.mode;
+    let mut parent = builder.submenu(&id, context.label(Label::Silence), context.disabled(&id));
+
+    for (entry_id, label) in [("Off", Label::Off), ("Skip", Label::SkipSilence), ("SpeedUp", Label::SpeedUpSilence)] {
+        parent.radio(entry_id, context.label(label), &id, context.selected(&id, entry_id, mode == entry_id), false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_levelling_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Levelling.to_string();
+    let mode = &context.settings.audio.levelling;
+    let mut parent = builder.submenu(&id, context.label(Label::Levelling), context.disabled(&id));
+
+    for (entry_id, label) in [("Off", Label::Off), ("Track", Label::LevelTracks), ("Album", Label::LevelAlbums)] {
+        parent.radio(entry_id, context.label(label), &id, context.selected(&id, entry_id, mode == entry_id), false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_theme_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
     let id = PlayerMenu::Theme.to_string();
-    let mut parent = builder.submenu(&id, "Theme", false);
+    let theme = &context.settings.theme;
+    let mut parent = builder.submenu(&id, context.label(Label::Theme), context.disabled(&id));
 
-    parent.radio("dark", "Dark", &id, &settings.theme == "dark", false);
-    parent.radio("light", "Light", &id, &settings.theme == "light", false);
+    parent.radio("dark", context.label(Label::Dark), &id, context.selected(&id, "dark", theme == "dark"), false);
+    parent.radio("light", context.label(Label::Light), &id, context.selected(&id, "light", theme == "light"), false);
 
     parent.build().unwrap();
 }
 
-fn create_playlist_menu(window_handle: isize, settings: &Settings) -> Menu {
-    let config = get_menu_config(&settings.theme);
+fn create_lang_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlayerMenu::Lang.to_string();
+    let mode = &context.settings.locale.mode;
+    let mut parent = builder.submenu(&id, context.label(Label::Lang), context.disabled(&id));
+
+    parent.radio("system", context.label(Label::System), &id, context.selected(&id, "system", mode == "system"), false);
+    for (lang, label) in [(Lang::En, Label::English), (Lang::Ja, Label::Japanese)] {
+        let code = lang.code();
+        parent.radio(code, context.label(label), &id, context.selected(&id, code, mode == code), false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_playlist_menu(window_handle: isize, context: &MenuContext) -> Menu {
+    let config = get_menu_config(&context.settings.theme);
     let mut builder = MenuBuilder::new_from_config(window_handle, config);
 
-    builder.text_with_accelerator(&PlaylistMenu::Remove.to_string(), "Remove", false, "Delete");
-    builder.text_with_accelerator(&PlaylistMenu::Trash.to_string(), "Trash", false, "Shift+Delete");
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Remove);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Trash);
+    builder.separator();
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::PlayNext);
+    create_repeat_submenu(&mut builder, context);
     builder.separator();
-    builder.text_with_accelerator(&PlaylistMenu::CopyFileName.to_string(), "Copy Name", false, "Ctrl+C");
-    builder.text_with_accelerator(&PlaylistMenu::CopyFullpath.to_string(), "Copy Full Path", false, "Ctrl+Shift+C");
-    builder.text_with_accelerator(&PlaylistMenu::Reveal.to_string(), "Reveal in File Explorer", false, "Ctrl+R");
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::CopyFileName);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::CopyFullpath);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Reveal);
     builder.separator();
-    builder.text_with_accelerator(&PlaylistMenu::Rename.to_string(), "Rename", false, "F2");
-    builder.text(&PlaylistMenu::Metadata.to_string(), "View Metadata", false);
-    builder.text(&PlaylistMenu::Convert.to_string(), "Convert", false);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Rename);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::RenameFromTags);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Metadata);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::EditTags);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Convert);
     builder.separator();
-    builder.text(&PlaylistMenu::Move.to_string(), "Move File", false);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Move);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Copy);
     builder.separator();
-    builder.text(&PlaylistMenu::RemoveAll.to_string(), "Clear Playlist", false);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Undo);
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::Redo);
+    builder.separator();
+    item(&mut builder, context, PLAY_LIST, PlaylistMenu::RemoveAll);
 
     builder.build().unwrap()
 }
 
-fn create_sort_menu(window_handle: isize, settings: &Settings) -> Menu {
+fn create_repeat_submenu(builder: &mut MenuBuilder, context: &MenuContext) {
+    let id = PlaylistMenu::Repeat.to_string();
+    let mut parent = builder.submenu(&id, context.label(Label::Repeat), context.disabled(&id));
+    let repeat = context.settings.playlist.repeat.to_string();
+
+    for (entry_id, label) in [("Off", Label::Off), ("One", Label::RepeatOne), ("All", Label::RepeatAll)] {
+        parent.radio(entry_id, context.label(label), &id, context.selected(&id, entry_id, entry_id == repeat), false);
+    }
+
+    parent.build().unwrap();
+}
+
+fn create_sort_menu(window_handle: isize, context: &MenuContext) -> Menu {
+    let settings = context.settings;
     let config = get_menu_config(&settings.theme);
     let mut builder = MenuBuilder::new_from_config(window_handle, config);
 
     let id = &PlaylistMenu::Sort.to_string();
+    let group_by = SortMenu::GroupBy.to_string();
 
-    builder.check(&SortMenu::GroupBy.to_string(), "Group By Directory", settings.groupBy, false);
+    builder.check(&group_by, context.label(Label::GroupBy), context.checked(&group_by, settings.sort.groupBy), context.disabled(&group_by));
     builder.separator();
-    builder.radio(&SortMenu::NameAsc.to_string(), "Name(Asc)", id, settings.order == "NameAsc", false);
-    builder.radio(&SortMenu::NameDesc.to_string(), "Name(Desc)", id, settings.order == "NameDesc", false);
-    builder.radio(&SortMenu::DateAsc.to_string(), "Date(Asc)", id, settings.order == "DateAsc", false);
-    builder.radio(&SortMenu::DateDesc.to_string(), "Date(Desc", id, settings.order == "DateDesc", false);
+    for order in [SortMenu::NameAsc, SortMenu::NameDesc, SortMenu::DateAsc, SortMenu::DateDesc] {
+        let label = context.label(order.label());
+        let order = order.to_string();
+        builder.radio(&order, label, id, context.selected(id, &order, settings.sort.order == order), context.disabled(&order));
+    }
 
     builder.build().unwrap()
 }
@@ -241,6 +767,7 @@ pub fn set_pause_thumbs(app: &tauri::AppHandle, receiver: &tauri::WebviewWindow,
 
 #[cfg(target_os = "windows")]
 fn get_thumb_buttons(app: &tauri::AppHandle, play: bool) -> [ThumbButton; 3] {
+    let lang = Lang::from_settings(&crate::settings::get(app));
     let backward = app.path().resolve("assets/backward.png", BaseDirectory::Resource).unwrap();
     let forward = app.path().resolve("assets/forward.png", BaseDirectory::Resource).unwrap();
 
@@ -249,17 +776,17 @@ fn get_thumb_buttons(app: &tauri::AppHandle, play: bool) -> [ThumbButton; 3] {
         [
             ThumbButton {
                 id: String::from("Previous"),
-                tool_tip: Some(String::from("Previous")),
+                tool_tip: Some(locale::label(lang, Label::Previous).to_string()),
                 icon: backward,
             },
             ThumbButton {
                 id: String::from("Play"),
-                tool_tip: Some(String::from("Play")),
+                tool_tip: Some(locale::label(lang, Label::Play).to_string()),
                 icon: play,
             },
             ThumbButton {
                 id: String::from("Next"),
-                tool_tip: Some(String::from("Next")),
+                tool_tip: Some(locale::label(lang, Label::Next).to_string()),
                 icon: forward,
             },
         ]
@@ -268,19 +795,109 @@ fn get_thumb_buttons(app: &tauri::AppHandle, play: bool) -> [ThumbButton; 3] {
         [
             ThumbButton {
                 id: String::from("Previous"),
-                tool_tip: Some(String::from("Previous")),
+                tool_tip: Some(locale::label(lang, Label::Previous).to_string()),
                 icon: backward,
             },
             ThumbButton {
                 id: String::from("Pause"),
-                tool_tip: Some(String::from("Pause")),
+                tool_tip: Some(locale::label(lang, Label::Pause).to_string()),
                 icon: pause,
             },
             ThumbButton {
                 id: String::from("Next"),
-                tool_tip: Some(String::from("Next")),
+                tool_tip: Some(locale::label(lang, Label::Next).to_string()),
                 icon: forward,
             },
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER_ITEMS: [PlayerMenu; 31] = [
+        PlayerMenu::PlaybackSpeed,
+        PlayerMenu::SeekSpeed,
+        PlayerMenu::TogglePlaylistWindow,
+        PlayerMenu::FitToWindow,
+        PlayerMenu::ToggleFullscreen,
+        PlayerMenu::Theme,
+        PlayerMenu::Lang,
+        PlayerMenu::Capture,
+        PlayerMenu::PictureInPicture,
+        PlayerMenu::Bookmarks,
+        PlayerMenu::AddBookmark,
+        PlayerMenu::EditBookmarks,
+        PlayerMenu::ImportBookmarks,
+        PlayerMenu::ExportBookmarks,
+        PlayerMenu::DetectScenes,
+        PlayerMenu::Silence,
+        PlayerMenu::Levelling,
+        PlayerMenu::AbLoop,
+        PlayerMenu::SetLoopStart,
+        PlayerMenu::SetLoopEnd,
+        PlayerMenu::ClearLoop,
+        PlayerMenu::ExportLoop,
+        PlayerMenu::Chapters,
+        PlayerMenu::NextChapter,
+        PlayerMenu::PreviousChapter,
+        PlayerMenu::Subtitles,
+        PlayerMenu::DelaySubtitles,
+        PlayerMenu::AdvanceSubtitles,
+        PlayerMenu::ResetSubtitleOffset,
+        PlayerMenu::SyncSubtitles,
+        PlayerMenu::ExportSubtitles,
+    ];
+
+    const PLAYLIST_ITEMS: [PlaylistMenu; 18] = [
+        PlaylistMenu::Remove,
+        PlaylistMenu::RemoveAll,
+        PlaylistMenu::Trash,
+        PlaylistMenu::CopyFileName,
+        PlaylistMenu::CopyFullpath,
+        PlaylistMenu::Reveal,
+        PlaylistMenu::Metadata,
+        PlaylistMenu::EditTags,
+        PlaylistMenu::Convert,
+        PlaylistMenu::Sort,
+        PlaylistMenu::Rename,
+        PlaylistMenu::RenameFromTags,
+        PlaylistMenu::Move,
+        PlaylistMenu::Copy,
+        PlaylistMenu::Undo,
+        PlaylistMenu::Redo,
+        PlaylistMenu::PlayNext,
+        PlaylistMenu::Repeat,
+    ];
+
+    const SORT_ITEMS: [SortMenu; 5] = [SortMenu::GroupBy, SortMenu::NameAsc, SortMenu::NameDesc, SortMenu::DateAsc, SortMenu::DateDesc];
+
+    /// Every item has a label in each language, and no two items of a menu look the same
+    fn assert_labels<T: MenuItemId>(items: &[T]) {
+        for lang in Lang::ALL {
+            let labels: Vec<&str> = items.iter().map(|item| locale::label(lang, item.label())).collect();
+
+            for (i, label) in labels.iter().enumerate() {
+                assert!(!label.trim().is_empty(), "{} has no {} label", items[i], lang.code());
+                assert!(!labels[..i].contains(label), "{} label of {} is used twice: {}", lang.code(), items[i], label);
+            }
+        }
+
+        for item in items {
+            assert_ne!(locale::label(Lang::En, item.label()), locale::label(Lang::Ja, item.label()), "{} is not translated", item);
+        }
+    }
+
+    #[test]
+    fn every_menu_item_has_a_label() {
+        assert_labels(&PLAYER_ITEMS);
+        assert_labels(&PLAYLIST_ITEMS);
+        assert_labels(&SORT_ITEMS);
+    }
+
+    #[test]
+    fn pause_is_not_stop_in_japanese() {
+        assert_eq!(locale::label(Lang::Ja, Label::Pause), "一時停止");
+    }
+}
@@ -0,0 +1,560 @@
+use crate::{hotkeys, settings::PLAYBACK_SPEED_RANGE, PLAYER};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tauri::{async_runtime, AppHandle, Emitter, EventTarget, Manager};
+use zbus::{
+    connection, interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, OwnedValue, Value},
+    Connection,
+};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.taltmediaplayer";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const TRACK_PATH: &str = "/org/mpris/MediaPlayer2/Track/Current";
+const MEDIA_CONTROL_EVENT_NAME: &str = "media-control";
+const MICROSECONDS: f64 = 1_000_000.0;
+
+/// What the Player window reports about the current file
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MediaSession {
+    pub status: PlaybackStatus,
+    pub file_path: String,
+    pub title: String,
+    pub artist: Vec<String>,
+    pub album: String,
+    pub art_url: Option<String>,
+    pub duration: f64,
+    pub position: f64,
+    pub volume: f64,
+    pub rate: f64,
+    #[serde(default)]
+    pub seeked: bool,
+    /// When `position` was reported, so that the position can be advanced while playing
+    #[serde(skip)]
+    pub reported_at: Option<Instant>,
+}
+
+impl MediaSession {
+    /// Position now, assuming playback went on at `rate` since it was reported
+    fn live_position(&self) -> f64 {
+        match (self.status, self.reported_at) {
+            (PlaybackStatus::Playing, Some(reported_at)) => {
+                let position = self.position + reported_at.elapsed().as_secs_f64() * self.rate;
+                if self.duration > 0.0 {
+                    position.min(self.duration)
+                } else {
+                    position
+                }
+            }
+            _ => self.position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+/// Requests from desktop media controls, forwarded to the Player window
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum MediaControl {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    Seek {
+        offset: f64,
+    },
+    SetPosition {
+        position: f64,
+    },
+    Volume {
+        volume: f64,
+    },
+    Rate {
+        rate: f64,
+    },
+}
+
+type SharedSession = Arc<Mutex<MediaSession>>;
+type ControlSink = Arc<dyn Fn(MediaControl) + Send + Sync>;
+
+struct Root {
+    raise: Arc<dyn Fn() + Send + Sync>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {
+        (self.raise)();
+    }
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "AltMediaPlayer"
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> &str {
+        "taltmediaplayer"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec![String::from("file")]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    session: SharedSession,
+    sink: ControlSink,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        (self.sink)(MediaControl::Next);
+    }
+
+    fn previous(&self) {
+        (self.sink)(MediaControl::Previous);
+    }
+
+    fn pause(&self) {
+        (self.sink)(MediaControl::Pause);
+    }
+
+    fn play_pause(&self) {
+        (self.sink)(MediaControl::PlayPause);
+    }
+
+    fn stop(&self) {
+        (self.sink)(MediaControl::Stop);
+    }
+
+    fn play(&self) {
+        (self.sink)(MediaControl::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        (self.sink)(MediaControl::Seek {
+            offset: offset as f64 / MICROSECONDS,
+        });
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if track_id.as_str() != TRACK_PATH || position < 0 {
+            return;
+        }
+
+        (self.sink)(MediaControl::SetPosition {
+            position: position as f64 / MICROSECONDS,
+        });
+    }
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        let status = self.session.lock().unwrap().status;
+        format!("{:?}", status)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.session.lock().unwrap().rate
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, rate: f64) {
+        if rate > 0.0 {
+            (self.sink)(MediaControl::Rate {
+                rate: rate.clamp(*PLAYBACK_SPEED_RANGE.start(), *PLAYBACK_SPEED_RANGE.end()),
+            });
+        }
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        metadata(&self.session.lock().unwrap())
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.session.lock().unwrap().volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        (self.sink)(MediaControl::Volume {
+            volume: volume.clamp(0.0, 1.0),
+        });
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        (self.session.lock().unwrap().live_position() * MICROSECONDS) as i64
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        *PLAYBACK_SPEED_RANGE.start()
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        *PLAYBACK_SPEED_RANGE.end()
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        !self.session.lock().unwrap().file_path.is_empty()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        !self.session.lock().unwrap().file_path.is_empty()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.session.lock().unwrap().duration > 0.0
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+fn metadata(session: &MediaSession) -> HashMap<String, OwnedValue> {
+    let mut metadata = HashMap::new();
+    let mut insert = |key: &str, value: Value<'_>| {
+        if let Ok(value) = OwnedValue::try_from(value) {
+            metadata.insert(key.to_string(), value);
+        }
+    };
+
+    if session.file_path.is_empty() {
+        insert("mpris:trackid", Value::from(ObjectPath::from_static_str_unchecked("/org/mpris/MediaPlayer2/TrackList/NoTrack")));
+        return metadata;
+    }
+
+    insert("mpris:trackid", Value::from(ObjectPath::from_static_str_unchecked(TRACK_PATH)));
+    insert("mpris:length", Value::from((session.duration * MICROSECONDS) as i64));
+    insert("xesam:url", Value::from(to_file_url(&session.file_path)));
+    insert("xesam:title", Value::from(session.title.clone()));
+
+    if !session.artist.is_empty() {
+        insert("xesam:artist", Value::from(session.artist.clone()));
+    }
+
+    if !session.album.is_empty() {
+        insert("xesam:album", Value::from(session.album.clone()));
+    }
+
+    if let Some(art_url) = &session.art_url {
+        insert("mpris:artUrl", Value::from(to_file_url(art_url)));
+    }
+
+    metadata
+}
+
+fn to_file_url(path: &str) -> String {
+    if path.contains("://") {
+        return path.to_string();
+    }
+
+    let mut url = String::from("file://");
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+pub struct Mpris {
+    session: SharedSession,
+    connection: async_runtime::Mutex<Option<Connection>>,
+    active: AtomicBool,
+}
+
+/// Serves MPRIS on the bus at `address`, or on the session bus from `DBUS_SESSION_BUS_ADDRESS` when none is given.
+/// Keeping the bus address and the control sink as parameters lets the service run against a private `dbus-daemon`.
+pub async fn serve(address: Option<&str>, session: SharedSession, sink: ControlSink, raise: Arc<dyn Fn() + Send + Sync>) -> zbus::Result<Connection> {
+    let builder = match address {
+        Some(address) => connection::Builder::address(address)?,
+        None => connection::Builder::session()?,
+    };
+
+    builder
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Root {
+                raise,
+            },
+        )?
+        .serve_at(
+            OBJECT_PATH,
+            Player {
+                session,
+                sink,
+            },
+        )?
+        .build()
+        .await
+}
+
+pub fn setup(app: &tauri::App) {
+    let session = SharedSession::default();
+    app.manage(Mpris {
+        session: session.clone(),
+        connection: async_runtime::Mutex::new(None),
+        active: AtomicBool::new(false),
+    });
+
+    let app_handle = app.handle().clone();
+    async_runtime::spawn(async move {
+        let control_app = app_handle.clone();
+        let sink: ControlSink = Arc::new(move |control| {
+            let _ = control_app.emit_to(
+                EventTarget::WebviewWindow {
+                    label: PLAYER.to_string(),
+                },
+                MEDIA_CONTROL_EVENT_NAME,
+                control,
+            );
+        });
+
+        let raise_app = app_handle.clone();
+        let raise = Arc::new(move || {
+            if let Some(player) = raise_app.get_webview_window(PLAYER) {
+                let _ = player.show();
+                let _ = player.set_focus();
+            }
+        });
+
+        if let Ok(connection) = serve(None, session, sink, raise).await {
+            let state = app_handle.state::<Mpris>();
+            *state.connection.lock().await = Some(connection);
+            state.active.store(true, Ordering::Relaxed);
+            // Hands the media keys over to the desktop
+            hotkeys::register(&app_handle);
+        }
+    });
+}
+
+/// Whether the player is on the session bus, where the desktop sends it the media keys
+pub fn is_active(app: &AppHandle) -> bool {
+    app.try_state::<Mpris>().is_some_and(|state| state.active.load(Ordering::Relaxed))
+}
+
+pub async fn update(app: &AppHandle, mut new_session: MediaSession) -> Result<(), String> {
+    new_session.reported_at = Some(Instant::now());
+    let state = app.state::<Mpris>();
+    let previous = std::mem::replace(&mut *state.session.lock().unwrap(), new_session.clone());
+
+    let connection = state.connection.lock().await;
+    let Some(connection) = connection.as_ref() else {
+        return Ok(());
+    };
+
+    let interface = connection.object_server().interface::<_, Player>(OBJECT_PATH).await.map_err(|e| e.to_string())?;
+    let player = interface.get().await;
+    let emitter = interface.signal_emitter();
+
+    if previous.status != new_session.status {
+        player.playback_status_changed(emitter).await.map_err(|e| e.to_string())?;
+    }
+
+    if previous.file_path != new_session.file_path
+        || previous.title != new_session.title
+        || previous.artist != new_session.artist
+        || previous.album != new_session.album
+        || previous.art_url != new_session.art_url
+        || previous.duration != new_session.duration
+    {
+        player.metadata_changed(emitter).await.map_err(|e| e.to_string())?;
+        player.can_play_changed(emitter).await.map_err(|e| e.to_string())?;
+        player.can_pause_changed(emitter).await.map_err(|e| e.to_string())?;
+        player.can_seek_changed(emitter).await.map_err(|e| e.to_string())?;
+    }
+
+    if previous.volume != new_session.volume {
+        player.volume_changed(emitter).await.map_err(|e| e.to_string())?;
+    }
+
+    if previous.rate != new_session.rate {
+        player.rate_changed(emitter).await.map_err(|e| e.to_string())?;
+    }
+
+    if new_session.seeked {
+        Player::seeked(emitter, (new_session.position * MICROSECONDS) as i64).await.map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+    use zbus::{fdo::PropertiesProxy, names::InterfaceName};
+
+    const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+    /// Private bus that is stopped when the test ends
+    struct Bus(Child);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    fn start_bus() -> Option<(Bus, String)> {
+        let mut child = Command::new("dbus-daemon").args(["--session", "--print-address", "--nofork"]).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().ok()?;
+        let stdout = child.stdout.take();
+        let bus = Bus(child);
+
+        let mut address = String::new();
+        BufReader::new(stdout?).read_line(&mut address).ok()?;
+        Some((bus, address.trim().to_string()))
+    }
+
+    #[test]
+    fn position_advances_only_while_playing() {
+        let mut session = MediaSession {
+            status: PlaybackStatus::Playing,
+            duration: 60.0,
+            position: 10.0,
+            rate: 2.0,
+            reported_at: Instant::now().checked_sub(std::time::Duration::from_secs(3)),
+            ..Default::default()
+        };
+        let position = session.live_position();
+        assert!((16.0..17.0).contains(&position), "{}", position);
+
+        session.position = 58.0;
+        assert_eq!(session.live_position(), 60.0);
+
+        session.status = PlaybackStatus::Paused;
+        assert_eq!(session.live_position(), 58.0);
+
+        session.status = PlaybackStatus::Playing;
+        session.reported_at = None;
+        assert_eq!(session.live_position(), 58.0);
+    }
+
+    #[test]
+    fn serve_forwards_controls_and_reports_the_session() {
+        let Some((_bus, address)) = start_bus() else {
+            eprintln!("dbus-daemon is not available, skipping");
+            return;
+        };
+
+        let session = SharedSession::new(Mutex::new(MediaSession {
+            status: PlaybackStatus::Playing,
+            file_path: String::from("/music/a b.mp3"),
+            title: String::from("Title"),
+            artist: vec![String::from("Artist")],
+            duration: 90.0,
+            rate: 1.0,
+            ..Default::default()
+        }));
+        let controls = Arc::new(Mutex::new(Vec::new()));
+        let received = controls.clone();
+        let sink: ControlSink = Arc::new(move |control| received.lock().unwrap().push(control));
+
+        tauri::async_runtime::block_on(async {
+            let _service = serve(Some(&address), session, sink, Arc::new(|| {})).await.unwrap();
+            let client = connection::Builder::address(address.as_str()).unwrap().build().await.unwrap();
+
+            client.call_method(Some(BUS_NAME), OBJECT_PATH, Some(PLAYER_INTERFACE), "PlayPause", &()).await.unwrap();
+            client.call_method(Some(BUS_NAME), OBJECT_PATH, Some(PLAYER_INTERFACE), "Next", &()).await.unwrap();
+            client.call_method(Some(BUS_NAME), OBJECT_PATH, Some(PLAYER_INTERFACE), "Seek", &(-5_000_000i64)).await.unwrap();
+
+            assert_eq!(
+                *controls.lock().unwrap(),
+                vec![
+                    MediaControl::PlayPause,
+                    MediaControl::Next,
+                    MediaControl::Seek {
+                        offset: -5.0
+                    }
+                ]
+            );
+
+            let properties = PropertiesProxy::builder(&client).destination(BUS_NAME).unwrap().path(OBJECT_PATH).unwrap().build().await.unwrap();
+            let interface = InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE);
+
+            let status = String::try_from(properties.get(interface.clone(), "PlaybackStatus").await.unwrap()).unwrap();
+            assert_eq!(status, "Playing");
+
+            let maximum_rate = f64::try_from(properties.get(interface.clone(), "MaximumRate").await.unwrap()).unwrap();
+            assert_eq!(maximum_rate, *PLAYBACK_SPEED_RANGE.end());
+
+            let metadata = HashMap::<String, OwnedValue>::try_from(properties.get(interface, "Metadata").await.unwrap()).unwrap();
+            assert_eq!(String::try_from(metadata["xesam:title"].try_clone().unwrap()).unwrap(), "Title");
+            assert_eq!(String::try_from(metadata["xesam:url"].try_clone().unwrap()).unwrap(), "file:///music/a%20b.mp3");
+            assert_eq!(i64::try_from(metadata["mpris:length"].try_clone().unwrap()).unwrap(), 90_000_000);
+            assert_eq!(Vec::<String>::try_from(metadata["xesam:artist"].try_clone().unwrap()).unwrap(), vec![String::from("Artist")]);
+        });
+    }
+}
@@ -0,0 +1,478 @@
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use strum_macros::Display;
+use tauri::{AppHandle, Emitter, Manager};
+
+const PLAYLIST_CHANGED_EVENT: &str = "playlist-changed";
+/// Seeds are kept within the integers a JavaScript number holds exactly
+const SEED_MASK: u64 = (1 << 53) - 1;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, Display)]
+pub enum RepeatMode {
+    Off,
+    One,
+    /// Wraps around at either end, as the playlist always did
+    #[default]
+    All,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    /// Id of the file in the Playlist window
+    pub id: String,
+    pub fullPath: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistSnapshot {
+    pub entries: Vec<PlaylistEntry>,
+    pub currentIndex: Option<usize>,
+    pub currentFile: Option<PlaylistEntry>,
+    pub shuffle: bool,
+    pub seed: u64,
+    pub repeat: RepeatMode,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaylistReplace {
+    pub entries: Vec<PlaylistEntry>,
+    pub currentIndex: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShuffleRequest {
+    pub shuffle: bool,
+    /// Replays the order of an earlier shuffle. A new round continues from the current seed when missing.
+    pub seed: Option<u64>,
+}
+
+/// SplitMix64. Small and fixed, so that a seed gives the same order in every version
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Number from 0 to bound - 1. The bound must not be 0
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Entries, current entry and play order. Free of Tauri types, so the order logic works without a webview.
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    entries: Vec<PlaylistEntry>,
+    /// Ids in the order they play. Same as the entries unless shuffled
+    order: Vec<String>,
+    /// Index of the current entry in the order. Shuffled entries before it were played in this round
+    position: Option<usize>,
+    shuffle: bool,
+    seed: u64,
+    rng: Rng,
+    repeat: RepeatMode,
+}
+
+impl Playlist {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            entries: Vec::new(),
+            order: Vec::new(),
+            position: None,
+            shuffle: false,
+            seed,
+            rng: Rng(seed),
+            repeat: RepeatMode::default(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&PlaylistEntry> {
+        let id = self.order.get(self.position?)?;
+        self.entries.iter().find(|entry| entry.id == *id)
+    }
+
+    pub fn current_index(&self) -> Option<usize> {
+        let id = self.order.get(self.position?)?;
+        self.entries.iter().position(|entry| entry.id == *id)
+    }
+
+    pub fn snapshot(&self) -> PlaylistSnapshot {
+        PlaylistSnapshot {
+            entries: self.entries.clone(),
+            currentIndex: self.current_index(),
+            currentFile: self.current().cloned(),
+            shuffle: self.shuffle,
+            seed: self.seed,
+            repeat: self.repeat,
+        }
+    }
+
+    /// Takes over the entries after they were added, removed, sorted or reordered. Entries already shuffled keep their place in the order.
+    pub fn replace(&mut self, entries: Vec<PlaylistEntry>, current_index: Option<usize>) {
+        let mut seen = HashSet::new();
+        self.entries = entries.into_iter().filter(|entry| seen.insert(entry.id.clone())).collect();
+
+        let current_id = self.current().map(|entry| entry.id.clone());
+
+        if self.shuffle {
+            let known: HashSet<String> = self.order.iter().cloned().collect();
+            self.order.retain(|id| seen.contains(id));
+            self.position = current_id.as_ref().and_then(|current_id| self.order.iter().position(|id| id == current_id));
+
+            // New entries join the part of the round that has not been played yet
+            let start = self.position.map_or(0, |position| position + 1);
+            for entry in self.entries.iter().filter(|entry| !known.contains(&entry.id)) {
+                let at = start + self.rng.below(self.order.len() - start + 1);
+                self.order.insert(at, entry.id.clone());
+            }
+        } else {
+            self.reset_order(current_id.as_deref());
+        }
+
+        match current_index.filter(|index| *index < self.entries.len()) {
+            Some(index) => {
+                let _ = self.jump(index);
+            }
+            None => self.position = None,
+        }
+    }
+
+    /// Makes the entry current. A shuffled entry is moved to play now, so the rest of the round stays unplayed.
+    pub fn jump(&mut self, index: usize) -> Result<(), String> {
+        let id = self.entries.get(index).map(|entry| entry.id.clone()).ok_or_else(|| format!("Invalid playlist index: {}", index))?;
+
+        if self.current().is_some_and(|current| current.id == id) {
+            return Ok(());
+        }
+
+        if self.shuffle {
+            self.position = Some(self.move_after_current(&id));
+        } else {
+            self.position = Some(index);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the entry to the order slot after the current one and returns that slot
+    fn move_after_current(&mut self, id: &str) -> usize {
+        if let Some(from) = self.order.iter().position(|order_id| order_id == id) {
+            self.order.remove(from);
+            if let Some(position) = self.position.filter(|position| from < *position) {
+                self.position = Some(position - 1);
+            }
+        }
+
+        let at = self.position.map_or(0, |position| position + 1);
+        self.order.insert(at, id.to_string());
+        at
+    }
+
+    /// Advances to the next entry. When the current one ended by itself, repeat-one plays it again and
+    /// repeat-off stops at the end of the list instead of wrapping.
+    pub fn next(&mut self, ended: bool) -> Option<&PlaylistEntry> {
+        if self.order.is_empty() {
+            return None;
+        }
+
+        if ended && self.repeat == RepeatMode::One && self.position.is_some() {
+            return self.current();
+        }
+
+        let next = self.position.map_or(0, |position| position + 1);
+        if next < self.order.len() {
+            self.position = Some(next);
+        } else if ended && self.repeat == RepeatMode::Off {
+            return None;
+        } else {
+            if self.shuffle {
+                self.start_round();
+            }
+            self.position = Some(0);
+        }
+
+        self.current()
+    }
+
+    /// Goes back through the order, wrapping to the last entry at the start
+    pub fn previous(&mut self) -> Option<&PlaylistEntry> {
+        if self.order.is_empty() {
+            return None;
+        }
+
+        self.position = match self.position {
+            Some(position) if position > 0 => Some(position - 1),
+            _ => Some(self.order.len() - 1),
+        };
+
+        self.current()
+    }
+
+    /// Puts the entries right after the current one, in the given order. Entries already in the list are moved.
+    pub fn insert_next(&mut self, entries: Vec<PlaylistEntry>) {
+        for entry in entries.into_iter().rev() {
+            if self.current().is_some_and(|current| current.id == entry.id) {
+                continue;
+            }
+
+            self.entries.retain(|existing| existing.id != entry.id);
+            let at = self.current_index().map_or(0, |index| index + 1);
+            let id = entry.id.clone();
+            self.entries.insert(at, entry);
+
+            if self.shuffle {
+                self.move_after_current(&id);
+            } else {
+                let current_id = self.current().map(|current| current.id.clone());
+                self.reset_order(current_id.as_deref());
+            }
+        }
+    }
+
+    /// Turning shuffle on starts a round from the current entry. A seed restarts the generator so the round can be reproduced.
+    pub fn set_shuffle(&mut self, shuffle: bool, seed: Option<u64>) {
+        if let Some(seed) = seed {
+            self.seed = seed & SEED_MASK;
+            self.rng = Rng(self.seed);
+        }
+
+        let current_id = self.current().map(|entry| entry.id.clone());
+        self.shuffle = shuffle;
+
+        if shuffle {
+            let mut rest: Vec<String> = self.entries.iter().map(|entry| entry.id.clone()).filter(|id| Some(id) != current_id.as_ref()).collect();
+            self.rng.shuffle(&mut rest);
+            self.order = current_id.iter().cloned().chain(rest).collect();
+            self.position = current_id.map(|_| 0);
+        } else {
+            self.reset_order(current_id.as_deref());
+        }
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    /// Plays the list order, keeping the current entry
+    fn reset_order(&mut self, current_id: Option<&str>) {
+        self.order = self.entries.iter().map(|entry| entry.id.clone()).collect();
+        self.position = current_id.and_then(|current_id| self.order.iter().position(|id| id == current_id));
+    }
+
+    /// Shuffles the whole list for the next round, without starting it with the entry that just played
+    fn start_round(&mut self) {
+        let last = self.position.and_then(|position| self.order.get(position).cloned());
+        self.rng.shuffle(&mut self.order);
+
+        if self.order.len() > 1 && self.order.first() == last.as_ref() {
+            let swap = 1 + self.rng.below(self.order.len() - 1);
+            self.order.swap(0, swap);
+        }
+    }
+}
+
+pub fn setup(app: &tauri::App) {
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos() as u64).unwrap_or_default() & SEED_MASK;
+    let mut playlist = Playlist::new(seed);
+
+    let saved = settings::get(app.handle()).playlist;
+    playlist.set_repeat(saved.repeat);
+    playlist.set_shuffle(saved.shuffle, None);
+
+    app.manage(Mutex::new(playlist));
+}
+
+/// Keeps the repeat and shuffle modes for the next start
+pub fn save_mode(app: &AppHandle, snapshot: &PlaylistSnapshot) -> Result<(), String> {
+    settings::update(app, |settings| {
+        settings.playlist.repeat = snapshot.repeat;
+        settings.playlist.shuffle = snapshot.shuffle;
+    })?;
+    Ok(())
+}
+
+pub fn get(app: &AppHandle) -> PlaylistSnapshot {
+    app.state::<Mutex<Playlist>>().lock().unwrap().snapshot()
+}
+
+/// Syncs the entries of the Playlist window. Not emitted back, since the window already has them.
+pub fn replace(app: &AppHandle, request: PlaylistReplace) -> PlaylistSnapshot {
+    let state = app.state::<Mutex<Playlist>>();
+    let mut playlist = state.lock().unwrap();
+    playlist.replace(request.entries, request.currentIndex);
+    playlist.snapshot()
+}
+
+/// Applies the change and sends the result to every window
+pub fn change<F>(app: &AppHandle, f: F) -> Result<PlaylistSnapshot, String>
+where
+    F: FnOnce(&mut Playlist) -> Result<(), String>,
+{
+    let snapshot = {
+        let state = app.state::<Mutex<Playlist>>();
+        let mut playlist = state.lock().unwrap();
+        f(&mut playlist)?;
+        playlist.snapshot()
+    };
+
+    app.emit(PLAYLIST_CHANGED_EVENT, &snapshot).map_err(|e| e.to_string())?;
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> PlaylistEntry {
+        PlaylistEntry {
+            id: id.to_string(),
+            fullPath: format!("/media/{}.mp4", id),
+        }
+    }
+
+    fn playlist(seed: u64, count: usize, current_index: Option<usize>) -> Playlist {
+        let mut playlist = Playlist::new(seed);
+        playlist.replace((0..count).map(|i| entry(&i.to_string())).collect(), current_index);
+        playlist
+    }
+
+    fn current_id(playlist: &Playlist) -> Option<String> {
+        playlist.current().map(|entry| entry.id.clone())
+    }
+
+    fn play(playlist: &mut Playlist, count: usize) -> Vec<String> {
+        (0..count).map(|_| playlist.next(false).unwrap().id.clone()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_order() {
+        let mut first = playlist(42, 20, Some(3));
+        let mut second = playlist(42, 20, Some(3));
+        first.set_shuffle(true, None);
+        second.set_shuffle(true, None);
+        assert_eq!(first.order, second.order);
+        assert_eq!(play(&mut first, 50), play(&mut second, 50));
+
+        // A seed given later replays the same order whatever was played before
+        first.set_shuffle(true, Some(7));
+        let mut third = playlist(1, 20, Some(first.current_index().unwrap()));
+        third.set_shuffle(true, Some(7));
+        assert_eq!(first.order, third.order);
+    }
+
+    #[test]
+    fn shuffle_plays_every_entry_once_per_round() {
+        let count = 10;
+        let mut playlist = playlist(5, count, Some(0));
+        playlist.set_shuffle(true, None);
+
+        let mut round = vec![current_id(&playlist).unwrap()];
+        round.extend(play(&mut playlist, count - 1));
+
+        for _ in 0..20 {
+            let unique: HashSet<&String> = round.iter().collect();
+            assert_eq!(unique.len(), count);
+
+            let last = round.last().cloned();
+            round = (0..count).map(|_| playlist.next(true).unwrap().id.clone()).collect();
+            assert_ne!(round.first(), last.as_ref());
+        }
+    }
+
+    #[test]
+    fn repeat_one_plays_the_entry_again_when_ended() {
+        let mut playlist = playlist(1, 3, Some(1));
+        playlist.set_repeat(RepeatMode::One);
+
+        assert_eq!(playlist.next(true).map(|entry| entry.id.clone()), Some(String::from("1")));
+        assert_eq!(playlist.next(false).map(|entry| entry.id.clone()), Some(String::from("2")));
+    }
+
+    #[test]
+    fn repeat_off_stops_at_the_end_when_ended() {
+        let mut playlist = playlist(1, 3, Some(2));
+        playlist.set_repeat(RepeatMode::Off);
+
+        assert!(playlist.next(true).is_none());
+        assert_eq!(current_id(&playlist), Some(String::from("2")));
+        // Skipping by hand still wraps
+        assert_eq!(playlist.next(false).map(|entry| entry.id.clone()), Some(String::from("0")));
+    }
+
+    #[test]
+    fn insert_next_in_list_order() {
+        let mut playlist = playlist(1, 5, Some(1));
+        playlist.insert_next(vec![entry("4"), entry("new")]);
+
+        let ids: Vec<&str> = playlist.entries.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["0", "1", "4", "new", "2", "3"]);
+        assert_eq!(playlist.current_index(), Some(1));
+        assert_eq!(play(&mut playlist, 4), ["4", "new", "2", "3"]);
+    }
+
+    #[test]
+    fn insert_next_when_shuffled() {
+        let mut playlist = playlist(9, 8, Some(2));
+        playlist.set_shuffle(true, None);
+        play(&mut playlist, 3);
+        let current = current_id(&playlist).unwrap();
+        let upcoming: Vec<String> = playlist.order[playlist.position.unwrap() + 1..].iter().filter(|id| *id != "0").cloned().collect();
+
+        playlist.insert_next(vec![entry("0"), entry("new"), entry(&current)]);
+
+        assert_eq!(current_id(&playlist), Some(current.clone()));
+        assert_eq!(playlist.entries.len(), 9);
+        let index = playlist.current_index().unwrap();
+        assert_eq!(playlist.entries[index + 1].id, "0");
+        assert_eq!(playlist.entries[index + 2].id, "new");
+
+        let mut expected = vec![String::from("0"), String::from("new")];
+        expected.extend(upcoming);
+        assert_eq!(play(&mut playlist, expected.len()), expected);
+    }
+
+    #[test]
+    fn replace_keeps_the_shuffled_order() {
+        let mut playlist = playlist(3, 10, Some(0));
+        playlist.set_shuffle(true, None);
+        play(&mut playlist, 4);
+        let order = playlist.order.clone();
+        let position = playlist.position.unwrap();
+        let current = current_id(&playlist).unwrap();
+
+        // Reversed as after a sort, one unplayed entry removed and one added
+        let removed = order[position + 1].clone();
+        let mut entries: Vec<PlaylistEntry> = playlist.entries.iter().rev().filter(|entry| entry.id != removed).cloned().collect();
+        entries.push(entry("new"));
+        let current_index = entries.iter().position(|entry| entry.id == current);
+        playlist.replace(entries, current_index);
+
+        assert_eq!(current_id(&playlist), Some(current));
+        assert_eq!(playlist.position, Some(position));
+        let without_new: Vec<String> = playlist.order.iter().filter(|id| *id != "new").cloned().collect();
+        let expected: Vec<String> = order.into_iter().filter(|id| *id != removed).collect();
+        assert_eq!(without_new, expected);
+        assert!(playlist.order.iter().position(|id| id == "new").unwrap() > position);
+    }
+}
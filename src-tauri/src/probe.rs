@@ -0,0 +1,120 @@
+use crate::{cache, shell};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Probe {
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(from = "FfprobeChapter")]
+pub struct Chapter {
+    pub id: i64,
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+struct FfprobeChapter {
+    id: i64,
+    start_time: String,
+    end_time: String,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+impl From<FfprobeChapter> for Chapter {
+    fn from(chapter: FfprobeChapter) -> Self {
+        Self {
+            id: chapter.id,
+            start: chapter.start_time.parse().unwrap_or_default(),
+            end: chapter.end_time.parse().unwrap_or_default(),
+            title: find_tag(&chapter.tags, "title").unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Stream {
+    pub index: usize,
+    #[serde(default)]
+    pub codec_type: String,
+    #[serde(default)]
+    pub codec_name: String,
+    #[serde(default)]
+    pub bit_rate: Option<String>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub disposition: HashMap<String, i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Format {
+    #[serde(default)]
+    pub filename: String,
+    #[serde(default)]
+    pub format_name: String,
+    #[serde(default)]
+    pub duration: Option<String>,
+    #[serde(default)]
+    pub bit_rate: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+impl Stream {
+    /// Cover images are stored as single frame video streams
+    pub fn is_cover(&self) -> bool {
+        self.disposition.get("attached_pic").is_some_and(|attached| *attached == 1)
+    }
+}
+
+impl Probe {
+    /// Looks up a container tag, falling back to the first audio stream as Ogg/Opus store tags there
+    pub fn tag(&self, key: &str) -> Option<String> {
+        find_tag(&self.format.tags, key).or_else(|| self.streams.iter().filter(|stream| stream.codec_type == "audio").find_map(|stream| find_tag(&stream.tags, key)))
+    }
+}
+
+fn find_tag(tags: &HashMap<String, String>, key: &str) -> Option<String> {
+    tags.iter().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+const PROBE_CACHE: &str = "probe";
+
+/// Runs ffprobe once per file version. The raw output is cached so that a cached entry parses exactly like a fresh one.
+pub async fn probe(app: &tauri::AppHandle, file_path: &str) -> Result<Probe, String> {
+    if let Some(probe) = cache::read(app, PROBE_CACHE, file_path, "json").and_then(|data| serde_json::from_slice(&data).ok()) {
+        return Ok(probe);
+    }
+
+    let (probe, output) = run_ffprobe(app, file_path).await?;
+
+    let _ = cache::write(app, PROBE_CACHE, file_path, "json", output.as_bytes());
+
+    Ok(probe)
+}
+
+/// Probes without the cache, for files that are about to be renamed or removed
+pub async fn probe_uncached(app: &tauri::AppHandle, file_path: &str) -> Result<Probe, String> {
+    Ok(run_ffprobe(app, file_path).await?.0)
+}
+
+async fn run_ffprobe(app: &tauri::AppHandle, file_path: &str) -> Result<(Probe, String), String> {
+    let args = ["-hide_banner", "-v", "error", "-print_format", "json", "-show_streams", "-show_format", "-show_chapters", "-i", file_path];
+    let output = shell::exec(app, "ffprobe", args.iter().map(|arg| arg.to_string()).collect()).await?;
+    let probe = serde_json::from_str(&output.stdout).map_err(|e| e.to_string())?;
+    Ok((probe, output.stdout))
+}
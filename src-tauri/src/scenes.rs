@@ -0,0 +1,251 @@
+use crate::{
+    bookmarks, cache, convert,
+    locale::{self, Label, Lang},
+    probe, settings, shell,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+const SCENE_CACHE: &str = "scenes";
+/// Seconds. Cuts closer than this to the previous marker are dropped
+const DEFAULT_MIN_INTERVAL: f64 = 2.0;
+/// Markers kept when a low threshold finds more, the highest scored ones win
+const MAX_MARKERS: usize = 200;
+const THUMBNAIL_WIDTH: u32 = 160;
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SceneDetection {
+    pub filePath: String,
+    /// Scene score from 0 to 1 a frame must exceed to count as a cut
+    pub threshold: f64,
+    pub minInterval: Option<f64>,
+    pub cancellationToken: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneMarker {
+    pub time: f64,
+    pub score: f64,
+    /// Cached image of the first frame of the scene
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum SceneTarget {
+    Bookmarks,
+    /// Path of the copy that receives the markers as chapters
+    Chapters(String),
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SceneSave {
+    pub filePath: String,
+    pub markers: Vec<SceneMarker>,
+    pub target: SceneTarget,
+}
+
+/// Runs ffmpeg scene detection on the video and returns the cuts found, each with a thumbnail
+pub async fn detect(app: &AppHandle, detection: SceneDetection) -> Result<Vec<SceneMarker>, String> {
+    if !(detection.threshold > 0.0 && detection.threshold < 1.0) {
+        return Err(format!("Invalid scene threshold: {}", detection.threshold));
+    }
+
+    let min_interval = detection.minInterval.unwrap_or(DEFAULT_MIN_INTERVAL).max(0.0);
+
+    let probe = probe::probe(app, &detection.filePath).await?;
+    let stream = convert::select(&probe, "video", None)?.ok_or_else(|| String::from("The file has no video stream"))?;
+    let map = format!("0:{}", stream.index);
+
+    // metadata=print logs the pts_time and score of every selected frame at info level
+    let filter = format!("select='gt(scene,{})',metadata=print:key=lavfi.scene_score", detection.threshold);
+    let args = ["-hide_banner", "-nostats", "-i", &detection.filePath, "-map", &map, "-an", "-sn", "-dn", "-vf", &filter, "-f", "null", "-"].iter().map(|arg| arg.to_string()).collect();
+    let output = shell::exec_with_token(app, "ffmpeg", args, detection.cancellationToken.clone()).await?;
+
+    let mut markers = thin_out(parse_scores(&output.stderr), min_interval);
+
+    if markers.len() > MAX_MARKERS {
+        markers.sort_by(|a, b| b.score.total_cmp(&a.score));
+        markers.truncate(MAX_MARKERS);
+        markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    for marker in markers.iter_mut() {
+        match thumbnail(app, &detection.filePath, &map, marker.time, &detection.cancellationToken).await {
+            Ok(path) => marker.thumbnail = Some(path),
+            // Stops on cancellation or a broken file instead of failing once per marker
+            Err(_) => break,
+        }
+    }
+    cache::prune(app, SCENE_CACHE);
+
+    Ok(markers)
+}
+
+fn parse_scores(log: &str) -> Vec<SceneMarker> {
+    let mut markers = Vec::new();
+    let mut time = None;
+
+    for line in log.lines() {
+        if let Some((_, rest)) = line.split_once("pts_time:") {
+            time = rest.split_whitespace().next().and_then(|value| value.parse::<f64>().ok());
+        } else if let Some((_, rest)) = line.split_once("lavfi.scene_score=") {
+            if let (Some(time), Ok(score)) = (time.take(), rest.trim().parse::<f64>()) {
+                markers.push(SceneMarker {
+                    time,
+                    score,
+                    thumbnail: None,
+                });
+            }
+        }
+    }
+
+    markers
+}
+
+/// Drops cuts that follow the start or the previous kept cut too closely, such as flashes and fast pans
+fn thin_out(markers: Vec<SceneMarker>, min_interval: f64) -> Vec<SceneMarker> {
+    let mut kept: Vec<SceneMarker> = Vec::new();
+
+    for marker in markers {
+        let previous = kept.last().map(|previous| previous.time).unwrap_or(0.0);
+        if marker.time - previous >= min_interval {
+            kept.push(marker);
+        }
+    }
+
+    kept
+}
+
+async fn thumbnail(app: &AppHandle, file_path: &str, map: &str, time: f64, cancellation_token: &str) -> Result<String, String> {
+    let path = cache::cache_path(app, SCENE_CACHE, file_path, &format!("{}.jpg", (time * 1000.0).round() as u64))?;
+    let path_str = path.to_string_lossy().to_string();
+
+    if path.exists() {
+        cache::touch(&path);
+        return Ok(path_str);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let scale = format!("scale={}:-2", THUMBNAIL_WIDTH);
+    let args = ["-hide_banner", "-v", "error", "-ss", &time.to_string(), "-i", file_path, "-map", map, "-frames:v", "1", "-vf", &scale, "-q:v", "5", "-y", &path_str]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+    if let Err(e) = shell::exec_with_token(app, "ffmpeg", args, cancellation_token.to_string()).await {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+
+    Ok(path_str)
+}
+
+/// Stores the markers as bookmarks of the file or as the chapters of a stream copy of it
+pub async fn save(app: &AppHandle, save: SceneSave) -> Result<(), String> {
+    if save.markers.is_empty() {
+        return Err(String::from("No scene markers to save"));
+    }
+
+    let scene = locale::label(Lang::from_settings(&settings::get(app)), Label::Scene);
+
+    match save.target {
+        SceneTarget::Bookmarks => {
+            let entries = save.markers.iter().enumerate().map(|(i, marker)| (marker.time, format!("{} {}", scene, i + 1))).collect();
+            bookmarks::add_all(app, &save.filePath, entries)
+        }
+        SceneTarget::Chapters(output) => write_chapters(app, &save.filePath, &save.markers, &output, scene).await,
+    }
+}
+
+async fn write_chapters(app: &AppHandle, file_path: &str, markers: &[SceneMarker], output: &str, scene: &str) -> Result<(), String> {
+    if Path::new(output) == Path::new(file_path) {
+        return Err(String::from("Chapters must be written to a copy of the file"));
+    }
+
+    let probe = probe::probe(app, file_path).await?;
+    let duration = probe.format.duration.as_ref().and_then(|duration| duration.parse::<f64>().ok()).ok_or_else(|| String::from("The duration of the file is unknown"))?;
+
+    // The first chapter covers the part before the first cut
+    let mut starts: Vec<f64> = markers.iter().map(|marker| marker.time).filter(|time| *time > 0.0 && *time < duration).collect();
+    starts.sort_by(|a, b| a.total_cmp(b));
+    starts.dedup();
+    starts.insert(0, 0.0);
+
+    let metadata_path = cache::cache_path(app, SCENE_CACHE, file_path, "ffmetadata")?;
+    if let Some(parent) = metadata_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&metadata_path, to_ffmetadata(&starts, duration, scene)).map_err(|e| e.to_string())?;
+
+    let metadata_str = metadata_path.to_string_lossy().to_string();
+    let args = ["-hide_banner", "-v", "error", "-i", file_path, "-f", "ffmetadata", "-i", &metadata_str, "-map", "0", "-map_metadata", "0", "-map_chapters", "1", "-c", "copy", "-y", output]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+    let result = shell::exec(app, "ffmpeg", args).await;
+    let _ = std::fs::remove_file(&metadata_path);
+
+    result.map(|_| ())
+}
+
+/// Chapters in milliseconds, each ending where the next one starts and titled with the localized word for scene
+fn to_ffmetadata(starts: &[f64], duration: f64, scene: &str) -> String {
+    let mut content = String::from(";FFMETADATA1\n");
+
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(duration);
+        content.push_str(&format!("[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={} {}\n", (start * 1000.0).round() as u64, (end * 1000.0).round() as u64, scene, i + 1));
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scores_from_the_log() {
+        let log = "\
+[Parsed_metadata_1 @ 0x1] frame:0    pts:1001    pts_time:1.001
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.512
+[Parsed_metadata_1 @ 0x1] frame:1    pts:90090   pts_time:90.09
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=bad
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.9
+[Parsed_metadata_1 @ 0x1] frame:2    pts:120000  pts_time:120
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=1.000000
+";
+        let markers: Vec<(f64, f64)> = parse_scores(log).iter().map(|marker| (marker.time, marker.score)).collect();
+
+        // A score without its own time is not paired with an earlier one
+        assert_eq!(markers, [(1.001, 0.512), (120.0, 1.0)]);
+    }
+
+    #[test]
+    fn thins_out_close_cuts() {
+        let markers = [0.5, 3.0, 4.0, 5.5, 10.0]
+            .iter()
+            .map(|time| SceneMarker {
+                time: *time,
+                score: 0.5,
+                thumbnail: None,
+            })
+            .collect();
+
+        let times: Vec<f64> = thin_out(markers, 2.0).iter().map(|marker| marker.time).collect();
+        assert_eq!(times, [3.0, 5.5, 10.0]);
+    }
+
+    #[test]
+    fn writes_chapters_up_to_the_duration() {
+        let content = to_ffmetadata(&[0.0, 12.3456], 60.0, "シーン");
+        assert_eq!(content, ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=12346\ntitle=シーン 1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=12346\nEND=60000\ntitle=シーン 2\n");
+    }
+}
@@ -0,0 +1,612 @@
+use crate::{
+    hotkeys,
+    keymap::{self, Keymap},
+    menu::{PLAYBACK_SPEEDS, SEEK_SPEEDS},
+    playlist::RepeatMode,
+    storage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, ops::RangeInclusive, path::PathBuf, sync::Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+const SETTINGS_FILE_NAME: &str = "taltmediaplayer.settings.json";
+const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+pub const SETTINGS_VERSION: u64 = 1;
+const THEMES: [&str; 2] = ["dark", "light"];
+const SORT_ORDERS: [&str; 4] = ["NameAsc", "NameDesc", "DateAsc", "DateDesc"];
+const LANGS: [&str; 2] = ["en", "ja"];
+const LEVELLING_MODES: [&str; 3] = ["Off", "Track", "Album"];
+const SILENCE_MODES: [&str; 3] = ["Off", "Skip", "SpeedUp"];
+const SILENCE_NOISE_RANGE: RangeInclusive<f64> = -90.0..=0.0;
+const SILENCE_DURATION_RANGE: RangeInclusive<f64> = 0.5..=60.0;
+pub const PLAYBACK_SPEED_RANGE: RangeInclusive<f64> = 0.0625..=16.0;
+const SEEK_SPEED_RANGE: RangeInclusive<f64> = 0.01..=600.0;
+const MAX_SPEED_PRESETS: usize = 20;
+
+/// Each entry upgrades a settings file from its index version to the next one. Versions without an entry only get the new version number.
+/// v0 files were written by the webview and already have the v1 shape, so they need no step.
+const MIGRATIONS: [fn(&mut Value); 0] = [];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Bounds {
+    pub width: f64,
+    pub height: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Sort {
+    pub order: String,
+    pub groupBy: bool,
+}
+
+/// Play mode of the playlist, restored on the next start
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct PlaylistSettings {
+    pub repeat: RepeatMode,
+    pub shuffle: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SpeedKind {
+    PlaybackSpeed,
+    SeekSpeed,
+}
+
+impl SpeedKind {
+    fn range(self) -> RangeInclusive<f64> {
+        match self {
+            SpeedKind::PlaybackSpeed => PLAYBACK_SPEED_RANGE,
+            SpeedKind::SeekSpeed => SEEK_SPEED_RANGE,
+        }
+    }
+
+    pub fn validate(self, speed: f64) -> Result<f64, String> {
+        if !self.range().contains(&speed) {
+            return Err(format!("{:?} must be between {} and {}: {}", self, self.range().start(), self.range().end(), speed));
+        }
+        Ok(speed)
+    }
+
+    fn validate_presets(self, presets: &[f64]) -> Result<(), String> {
+        if presets.is_empty() || presets.len() > MAX_SPEED_PRESETS {
+            return Err(format!("{:?} presets must have 1 to {} values", self, MAX_SPEED_PRESETS));
+        }
+
+        for (i, preset) in presets.iter().enumerate() {
+            self.validate(*preset)?;
+            if presets[..i].contains(preset) {
+                return Err(format!("Duplicate {:?} preset: {}", self, preset));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SilenceSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !SILENCE_MODES.contains(&self.mode.as_str()) {
+            return Err(format!("Invalid silence mode: {}", self.mode));
+        }
+
+        SpeedKind::PlaybackSpeed.validate(self.speed)?;
+
+        if !SILENCE_NOISE_RANGE.contains(&self.noise) {
+            return Err(format!("Silence noise must be between {} and {} dB: {}", SILENCE_NOISE_RANGE.start(), SILENCE_NOISE_RANGE.end(), self.noise));
+        }
+
+        if !SILENCE_DURATION_RANGE.contains(&self.minDuration) {
+            return Err(format!("Silence minDuration must be between {} and {}: {}", SILENCE_DURATION_RANGE.start(), SILENCE_DURATION_RANGE.end(), self.minDuration));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomSpeed {
+    pub kind: SpeedKind,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpeedPresets {
+    pub kind: SpeedKind,
+    pub presets: Vec<f64>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub fitToWindow: bool,
+    pub playbackSpeed: f64,
+    pub seekSpeed: f64,
+    pub playbackSpeedPresets: Vec<f64>,
+    pub seekSpeedPresets: Vec<f64>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub volume: f64,
+    pub ampLevel: f64,
+    pub mute: bool,
+    /// Loudness levelling: "Off", "Track" or "Album"
+    pub levelling: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SilenceSettings {
+    /// What the player does with silent parts: "Off", "Skip" or "SpeedUp"
+    pub mode: String,
+    /// Playback speed inside silent parts in "SpeedUp" mode
+    pub speed: f64,
+    /// Level in dB below which audio counts as silence
+    pub noise: f64,
+    /// Seconds of silence needed before a part counts as silent
+    pub minDuration: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Locale {
+    pub mode: String,
+    pub lang: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u64,
+    pub bounds: Bounds,
+    pub playlistBounds: Bounds,
+    pub isMaximized: bool,
+    pub playlistVisible: bool,
+    pub theme: String,
+    pub sort: Sort,
+    pub playlist: PlaylistSettings,
+    pub video: VideoSettings,
+    pub audio: AudioSettings,
+    pub silence: SilenceSettings,
+    pub defaultPath: String,
+    pub locale: Locale,
+    pub globalShortcuts: BTreeMap<String, String>,
+    pub keymap: Keymap,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            width: 1200.0,
+            height: 800.0,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            order: String::from("NameAsc"),
+            groupBy: false,
+        }
+    }
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            fitToWindow: true,
+            playbackSpeed: 1.0,
+            seekSpeed: 10.0,
+            playbackSpeedPresets: PLAYBACK_SPEEDS.to_vec(),
+            seekSpeedPresets: SEEK_SPEEDS.to_vec(),
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            ampLevel: 0.07,
+            mute: false,
+            levelling: String::from("Off"),
+        }
+    }
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            mode: String::from("Off"),
+            speed: 4.0,
+            noise: -35.0,
+            minDuration: 2.0,
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            mode: String::from("system"),
+            lang: String::from("en"),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            bounds: Bounds::default(),
+            playlistBounds: Bounds {
+                width: 400.0,
+                height: 700.0,
+                x: 0.0,
+                y: 0.0,
+            },
+            isMaximized: false,
+            playlistVisible: true,
+            theme: String::from("dark"),
+            sort: Sort::default(),
+            playlist: PlaylistSettings::default(),
+            video: VideoSettings::default(),
+            audio: AudioSettings::default(),
+            silence: SilenceSettings::default(),
+            defaultPath: String::new(),
+            locale: Locale::default(),
+            globalShortcuts: hotkeys::default_bindings(),
+            keymap: Keymap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !THEMES.contains(&self.theme.as_str()) {
+            return Err(format!("Invalid theme: {}", self.theme));
+        }
+
+        if !SORT_ORDERS.contains(&self.sort.order.as_str()) {
+            return Err(format!("Invalid sort order: {}", self.sort.order));
+        }
+
+        SpeedKind::PlaybackSpeed.validate(self.video.playbackSpeed)?;
+        SpeedKind::SeekSpeed.validate(self.video.seekSpeed)?;
+        SpeedKind::PlaybackSpeed.validate_presets(&self.video.playbackSpeedPresets)?;
+        SpeedKind::SeekSpeed.validate_presets(&self.video.seekSpeedPresets)?;
+
+        if !(0.0..=1.0).contains(&self.audio.volume) {
+            return Err(format!("Invalid volume: {}", self.audio.volume));
+        }
+
+        if !(0.0..=1.0).contains(&self.audio.ampLevel) {
+            return Err(format!("Invalid ampLevel: {}", self.audio.ampLevel));
+        }
+
+        if !LEVELLING_MODES.contains(&self.audio.levelling.as_str()) {
+            return Err(format!("Invalid levelling mode: {}", self.audio.levelling));
+        }
+
+        self.silence.validate()?;
+
+        if self.locale.mode != "system" && !LANGS.contains(&self.locale.mode.as_str()) {
+            return Err(format!("Invalid locale mode: {}", self.locale.mode));
+        }
+
+        if !LANGS.contains(&self.locale.lang.as_str()) {
+            return Err(format!("Invalid locale lang: {}", self.locale.lang));
+        }
+
+        hotkeys::validate(&self.globalShortcuts)?;
+
+        keymap::validate(&self.keymap)?;
+
+        Ok(())
+    }
+
+    /// Replaces invalid values from a file on disk with defaults instead of discarding the whole file
+    fn sanitize(mut self) -> Self {
+        let defaults = Settings::default();
+
+        if !THEMES.contains(&self.theme.as_str()) {
+            self.theme = defaults.theme;
+        }
+        if !SORT_ORDERS.contains(&self.sort.order.as_str()) {
+            self.sort.order = defaults.sort.order;
+        }
+        if SpeedKind::PlaybackSpeed.validate(self.video.playbackSpeed).is_err() {
+            self.video.playbackSpeed = defaults.video.playbackSpeed;
+        }
+        if SpeedKind::SeekSpeed.validate(self.video.seekSpeed).is_err() {
+            self.video.seekSpeed = defaults.video.seekSpeed;
+        }
+        if SpeedKind::PlaybackSpeed.validate_presets(&self.video.playbackSpeedPresets).is_err() {
+            self.video.playbackSpeedPresets = defaults.video.playbackSpeedPresets;
+        }
+        if SpeedKind::SeekSpeed.validate_presets(&self.video.seekSpeedPresets).is_err() {
+            self.video.seekSpeedPresets = defaults.video.seekSpeedPresets;
+        }
+        self.audio.volume = self.audio.volume.clamp(0.0, 1.0);
+        self.audio.ampLevel = self.audio.ampLevel.clamp(0.0, 1.0);
+        if !LEVELLING_MODES.contains(&self.audio.levelling.as_str()) {
+            self.audio.levelling = defaults.audio.levelling;
+        }
+        if self.silence.validate().is_err() {
+            self.silence = defaults.silence;
+        }
+        if self.locale.mode != "system" && !LANGS.contains(&self.locale.mode.as_str()) {
+            self.locale.mode = defaults.locale.mode;
+        }
+        if !LANGS.contains(&self.locale.lang.as_str()) {
+            self.locale.lang = defaults.locale.lang;
+        }
+        if hotkeys::validate(&self.globalShortcuts).is_err() {
+            self.globalShortcuts = defaults.globalShortcuts;
+        }
+        if keymap::validate(&self.keymap).is_err() {
+            self.keymap = defaults.keymap;
+        }
+
+        self
+    }
+}
+
+fn migrate(mut value: Value) -> Value {
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    while version < SETTINGS_VERSION {
+        if let Some(migration) = MIGRATIONS.get(version as usize) {
+            migration(&mut value);
+        }
+        version += 1;
+        value["version"] = Value::from(version);
+    }
+
+    value
+}
+
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+pub fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join("temp").join(SETTINGS_FILE_NAME))
+}
+
+fn load(app: &AppHandle) -> Settings {
+    let Ok(path) = settings_path(app) else {
+        return Settings::default();
+    };
+
+    storage::read_json::<Value>(&path).map(parse).unwrap_or_default()
+}
+
+/// Upgrades and repairs a settings file. Anything that is not a settings object gives the defaults.
+fn parse(value: Value) -> Settings {
+    if !value.is_object() {
+        return Settings::default();
+    }
+
+    repair(migrate(value)).sanitize()
+}
+
+/// Takes the values of the file one at a time over the defaults and drops those that do not deserialize, so a wrong-typed field only resets itself
+fn repair(value: Value) -> Settings {
+    if let Ok(settings) = serde_json::from_value::<Settings>(value.clone()) {
+        return settings;
+    }
+
+    let mut leaves = Vec::new();
+    collect_leaves(value, &mut Vec::new(), &mut leaves);
+
+    let mut repaired = serde_json::to_value(Settings::default()).unwrap_or_default();
+    for (path, leaf) in leaves {
+        let patch = path.into_iter().rev().fold(leaf, |value, key| Value::Object(serde_json::Map::from_iter([(key, value)])));
+        let mut candidate = repaired.clone();
+        merge(&mut candidate, patch);
+        if serde_json::from_value::<Settings>(candidate.clone()).is_ok() {
+            repaired = candidate;
+        }
+    }
+
+    serde_json::from_value(repaired).unwrap_or_default()
+}
+
+/// Values that are not objects, or empty objects, with the keys leading to them
+fn collect_leaves(value: Value, path: &mut Vec<String>, leaves: &mut Vec<(Vec<String>, Value)>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                path.push(key);
+                collect_leaves(value, path, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.clone(), value)),
+    }
+}
+
+pub fn setup(app: &tauri::App) {
+    let settings = load(app.handle());
+    app.manage(Mutex::new(settings));
+}
+
+pub fn get(app: &AppHandle) -> Settings {
+    app.state::<Mutex<Settings>>().lock().unwrap().clone()
+}
+
+/// Applies a change, rejects it if the result is invalid, then persists and broadcasts the new settings
+pub fn update<F: FnOnce(&mut Settings)>(app: &AppHandle, f: F) -> Result<Settings, String> {
+    let state = app.state::<Mutex<Settings>>();
+    let mut settings = state.lock().unwrap();
+
+    let mut new_settings = settings.clone();
+    f(&mut new_settings);
+    new_settings.version = SETTINGS_VERSION;
+    new_settings.validate()?;
+
+    storage::write_json(&settings_path(app)?, &new_settings)?;
+    *settings = new_settings.clone();
+    drop(settings);
+
+    app.emit(SETTINGS_CHANGED_EVENT, &new_settings).map_err(|e| e.to_string())?;
+
+    Ok(new_settings)
+}
+
+/// Replaces the presets of a speed submenu, listed from slowest to fastest
+pub fn set_speed_presets(app: &AppHandle, presets: SpeedPresets) -> Result<Settings, String> {
+    let mut values = presets.presets;
+    values.sort_by(f64::total_cmp);
+
+    update(app, |settings| match presets.kind {
+        SpeedKind::PlaybackSpeed => settings.video.playbackSpeedPresets = values,
+        SpeedKind::SeekSpeed => settings.video.seekSpeedPresets = values,
+    })
+}
+
+/// Merges a partial settings object into the current settings
+pub fn update_from_json(app: &AppHandle, patch: Value) -> Result<Settings, String> {
+    let mut value = serde_json::to_value(get(app)).map_err(|e| e.to_string())?;
+    merge(&mut value, patch);
+    let patched: Settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    update(app, |settings| *settings = patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(Settings::default().validate(), Ok(()));
+        assert_eq!(Settings::default().sanitize(), Settings::default());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_values() {
+        let invalid: [fn(&mut Settings); 7] = [
+            |settings| settings.theme = String::from("blue"),
+            |settings| settings.video.playbackSpeed = 32.0,
+            |settings| settings.video.seekSpeedPresets = Vec::new(),
+            |settings| settings.video.playbackSpeedPresets = vec![1.0, 1.0],
+            |settings| settings.audio.volume = 1.5,
+            |settings| settings.silence.noise = 10.0,
+            |settings| settings.locale.mode = String::from("fr"),
+        ];
+
+        for change in invalid {
+            let mut settings = Settings::default();
+            change(&mut settings);
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn sanitize_replaces_only_invalid_values() {
+        let settings = Settings {
+            theme: String::from("light"),
+            sort: Sort {
+                order: String::from("Random"),
+                ..Default::default()
+            },
+            video: VideoSettings {
+                playbackSpeed: 0.0,
+                ..Default::default()
+            },
+            audio: AudioSettings {
+                volume: -1.0,
+                ..Default::default()
+            },
+            silence: SilenceSettings {
+                mode: String::from("Loud"),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let sanitized = settings.sanitize();
+
+        assert_eq!(sanitized.theme, "light");
+        assert_eq!(sanitized.sort.order, "NameAsc");
+        assert_eq!(sanitized.video.playbackSpeed, 1.0);
+        assert_eq!(sanitized.audio.volume, 0.0);
+        assert_eq!(sanitized.silence, SilenceSettings::default());
+        assert_eq!(sanitized.validate(), Ok(()));
+    }
+
+    #[test]
+    fn parse_migrates_files_without_a_version() {
+        let settings = parse(json!({ "theme": "light", "audio": { "volume": 0.5 } }));
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.theme, "light");
+        assert_eq!(settings.audio.volume, 0.5);
+        assert_eq!(settings.audio.ampLevel, AudioSettings::default().ampLevel);
+
+        assert_eq!(migrate(json!({ "version": SETTINGS_VERSION, "theme": "light" }))["theme"], "light");
+        assert_eq!(parse(json!([1, 2])), Settings::default());
+        assert_eq!(parse(json!({ "theme": 1 })), Settings::default());
+    }
+
+    #[test]
+    fn parse_resets_only_wrong_typed_fields() {
+        let settings = parse(json!({
+            "version": SETTINGS_VERSION,
+            "theme": 1,
+            "playlistVisible": false,
+            "sort": { "order": "NameDesc", "groupBy": "yes" },
+            "playlist": { "repeat": "Twice", "shuffle": true },
+            "audio": { "volume": "loud", "mute": true },
+            "video": { "seekSpeedPresets": [5, "x"], "seekSpeed": 20 },
+            "keymap": { "Player": { "Capture": 5, "ToggleFullscreen": "F10" } },
+            "unknown": { "kept": false },
+        }));
+
+        assert_eq!(settings.theme, "dark");
+        assert!(!settings.playlistVisible);
+        assert_eq!(settings.sort.order, "NameDesc");
+        assert!(!settings.sort.groupBy);
+        assert_eq!(settings.playlist.repeat, RepeatMode::All);
+        assert!(settings.playlist.shuffle);
+        assert_eq!(settings.audio.volume, 1.0);
+        assert!(settings.audio.mute);
+        assert_eq!(settings.video.seekSpeedPresets, VideoSettings::default().seekSpeedPresets);
+        assert_eq!(settings.video.seekSpeed, 20.0);
+        assert_eq!(settings.keymap, Keymap::from([(String::from("Player"), BTreeMap::from([(String::from("ToggleFullscreen"), String::from("F10"))]))]));
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn merge_keeps_fields_missing_from_the_patch() {
+        let mut value = json!({ "audio": { "volume": 1.0, "mute": false }, "theme": "dark" });
+        merge(&mut value, json!({ "audio": { "mute": true } }));
+
+        assert_eq!(value, json!({ "audio": { "volume": 1.0, "mute": true }, "theme": "dark" }));
+    }
+}
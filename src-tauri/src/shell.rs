@@ -1,7 +1,19 @@
-use std::path::PathBuf;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::{
+    io::Read,
+    path::PathBuf,
+    process::{ChildStdout, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use tauri::Manager;
 use zouni::process::{Output, SpawnOption};
 
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+static TOKEN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 pub async fn spawn(app: &tauri::AppHandle, option: SpawnOption) -> Result<Output, Output> {
     let mut modified_option = option.clone();
     let command_name = PathBuf::from(option.program).components().next_back().unwrap().as_os_str().to_string_lossy().into_owned();
@@ -10,6 +22,69 @@ pub async fn spawn(app: &tauri::AppHandle, option: SpawnOption) -> Result<Output
     zouni::process::spawn(modified_option).await
 }
 
+pub async fn exec(app: &tauri::AppHandle, program: &str, args: Vec<String>) -> Result<Output, String> {
+    exec_with_token(app, program, args, new_cancellation_token(program)).await
+}
+
+pub async fn exec_with_token(app: &tauri::AppHandle, program: &str, args: Vec<String>, cancellation_token: String) -> Result<Output, String> {
+    let command_path = relative_command_path(app, program.to_string())?;
+    let option = SpawnOption {
+        program: command_path.to_string_lossy().to_string(),
+        args: Some(args),
+        cancellation_token,
+    };
+    zouni::process::spawn(option).await.map_err(|output| {
+        if output.stderr.is_empty() {
+            format!("{} failed", program)
+        } else {
+            output.stderr
+        }
+    })
+}
+
+/// Runs the program and hands its stdout to `read` as it is produced, for output that is binary or too large to collect as text.
+/// Blocks until the program exits, and reports its stderr when it fails.
+pub fn exec_streaming<T, F>(app: &tauri::AppHandle, program: &str, args: Vec<String>, read: F) -> Result<T, String>
+where
+    F: FnOnce(ChildStdout) -> Result<T, String>,
+{
+    let mut command = Command::new(relative_command_path(app, program.to_string())?);
+    command.args(args).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+
+    // Read on its own thread, so that a program writing many errors never waits on a full pipe
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    let result = read(child.stdout.take().unwrap());
+    // A reader that gave up stops the program, and its error says more than the killed status
+    let killed = result.is_err() && child.kill().is_ok();
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    let stderr = errors.join().unwrap_or_default();
+
+    if !killed && !status.success() {
+        return Err(if stderr.is_empty() {
+            format!("{} failed", program)
+        } else {
+            stderr
+        });
+    }
+
+    result
+}
+
+pub fn new_cancellation_token(program: &str) -> String {
+    format!("{}-{}-{}", program, std::process::id(), TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
 pub fn kill(cancellation_token: String) -> Result<(), String> {
     zouni::process::kill(cancellation_token)
 }
@@ -0,0 +1,163 @@
+use crate::{
+    cache, convert,
+    probe::{self, Stream},
+    settings::{self, SilenceSettings},
+    shell,
+};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const SILENCE_CACHE: &str = "silence";
+/// Seconds of each silence left in place, so that words at its edges are not cut
+const SILENCE_PADDING: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SilenceInterval {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SilenceRequest {
+    pub filePath: String,
+    /// Lets the player stop the detection when another file starts
+    pub cancellationToken: String,
+}
+
+/// Silent parts of the default audio stream, detected with the thresholds in the settings
+pub async fn get(app: &AppHandle, request: SilenceRequest) -> Result<Vec<SilenceInterval>, String> {
+    let probe = probe::probe(app, &request.filePath).await?;
+    let Some(stream) = convert::select(&probe, "audio", None)? else {
+        return Ok(Vec::new());
+    };
+
+    detect(app, &request.filePath, stream, request.cancellationToken).await
+}
+
+/// Runs silencedetect on the stream, or reads the result of an earlier run with the same thresholds, and returns the intervals minus the padding
+pub async fn detect(app: &AppHandle, file_path: &str, stream: &Stream, cancellation_token: String) -> Result<Vec<SilenceInterval>, String> {
+    let options = settings::get(app).silence;
+    let extension = format!("{}.{}dB.{}s.json", stream.index, options.noise, options.minDuration);
+
+    let intervals = match cache::read(app, SILENCE_CACHE, file_path, &extension).and_then(|data| serde_json::from_slice::<Vec<SilenceInterval>>(&data).ok()) {
+        Some(intervals) => intervals,
+        None => {
+            let intervals = run_silencedetect(app, file_path, stream, &options, cancellation_token).await?;
+            let data = serde_json::to_vec(&intervals).map_err(|e| e.to_string())?;
+            cache::write(app, SILENCE_CACHE, file_path, &extension, &data)?;
+            intervals
+        }
+    };
+
+    Ok(intervals
+        .iter()
+        .map(|interval| SilenceInterval {
+            start: if interval.start > 0.0 {
+                interval.start + SILENCE_PADDING
+            } else {
+                0.0
+            },
+            end: interval.end - SILENCE_PADDING,
+        })
+        .filter(|interval| interval.end > interval.start)
+        .collect())
+}
+
+async fn run_silencedetect(app: &AppHandle, file_path: &str, stream: &Stream, options: &SilenceSettings, cancellation_token: String) -> Result<Vec<SilenceInterval>, String> {
+    let map = format!("0:{}", stream.index);
+    let filter = format!("silencedetect=noise={}dB:d={}", options.noise, options.minDuration);
+    // silencedetect reports at info level
+    let args = ["-hide_banner", "-nostats", "-i", file_path, "-map", &map, "-vn", "-sn", "-dn", "-af", &filter, "-f", "null", "-"].iter().map(|arg| arg.to_string()).collect();
+    let output = shell::exec_with_token(app, "ffmpeg", args, cancellation_token).await?;
+
+    let duration = probe::probe(app, file_path).await?.format.duration.and_then(|duration| duration.parse::<f64>().ok());
+
+    Ok(parse(&output.stderr, duration))
+}
+
+/// Reads "silence_start: 1.23" and "silence_end: 4.56 | silence_duration: 3.33" lines. A silence still open at the end lasts until the end of the file.
+fn parse(log: &str, duration: Option<f64>) -> Vec<SilenceInterval> {
+    let mut intervals = Vec::new();
+    let mut start = None;
+
+    for line in log.lines() {
+        if let Some((_, rest)) = line.split_once("silence_start:") {
+            start = rest.split_whitespace().next().and_then(|value| value.parse::<f64>().ok()).map(|start| start.max(0.0));
+        } else if let Some((_, rest)) = line.split_once("silence_end:") {
+            let end = rest.split_whitespace().next().and_then(|value| value.parse::<f64>().ok());
+            if let (Some(start), Some(end)) = (start.take(), end) {
+                intervals.push(SilenceInterval {
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    if let (Some(start), Some(end)) = (start, duration) {
+        intervals.push(SilenceInterval {
+            start,
+            end,
+        });
+    }
+
+    intervals
+}
+
+/// select/aselect expression that drops the frames inside the intervals
+pub fn drop_expression(intervals: &[SilenceInterval]) -> String {
+    let ranges: Vec<String> = intervals.iter().map(|interval| format!("between(t,{},{})", interval.start, interval.end)).collect();
+    format!("'not({})'", ranges.join("+"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "[silencedetect @ 0x1] silence_start: 1.5
+[silencedetect @ 0x1] silence_end: 3.25 | silence_duration: 1.75
+size=N/A time=00:00:10.00 bitrate=N/A speed= 500x
+[silencedetect @ 0x1] silence_start: -0.02
+[silencedetect @ 0x1] silence_end: 0.5 | silence_duration: 0.52
+[silencedetect @ 0x1] silence_start: 8
+";
+
+    #[test]
+    fn parse_pairs_starts_and_ends() {
+        let intervals = parse(LOG, None);
+        assert_eq!(
+            intervals,
+            vec![
+                SilenceInterval {
+                    start: 1.5,
+                    end: 3.25
+                },
+                SilenceInterval {
+                    start: 0.0,
+                    end: 0.5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_closes_trailing_silence_at_duration() {
+        let intervals = parse(LOG, Some(10.0));
+        assert_eq!(
+            intervals.last(),
+            Some(&SilenceInterval {
+                start: 8.0,
+                end: 10.0
+            })
+        );
+        assert_eq!(intervals.len(), 3);
+    }
+
+    #[test]
+    fn parse_ignores_unpaired_and_invalid_lines() {
+        let log = "silence_end: 2 | silence_duration: 2\nsilence_start: abc\nsilence_end: 4\n";
+        assert!(parse(log, Some(5.0)).is_empty());
+        assert!(parse("", Some(5.0)).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, ".bak")
+}
+
+/// Unique file next to `path`, so that concurrent writers never share a temporary file
+pub fn temp_path(path: &Path) -> PathBuf {
+    sibling(path, &format!(".{}.{}.tmp", std::process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Writes to a temporary file next to `path` and renames it over the original, so readers never see a partial file
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let temp = temp_path(path);
+    let mut file = File::create(&temp).map_err(|e| e.to_string())?;
+    file.write_all(data).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    drop(file);
+
+    std::fs::rename(&temp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        e.to_string()
+    })
+}
+
+/// Atomically replaces a JSON file, first keeping the current file as `.bak` if it still parses
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+
+    if let Ok(current) = std::fs::read(path) {
+        if serde_json::from_slice::<serde_json::Value>(&current).is_ok() {
+            write_atomic(&backup_path(path), &current)?;
+        }
+    }
+
+    write_atomic(path, &data)
+}
+
+/// Reads a JSON file, falling back to its `.bak` when the main file is missing or does not parse
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    parse_json(path).or_else(|| parse_json(&backup_path(path)))
+}
+
+fn parse_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = std::fs::read(path).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_paths_are_unique() {
+        let path = Path::new("/data/settings.json");
+        let (first, second) = (temp_path(path), temp_path(path));
+
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), path.parent());
+        assert!(first.to_string_lossy().starts_with("/data/settings.json."));
+    }
+
+    #[test]
+    fn read_falls_back_to_the_backup() {
+        let dir = std::env::temp_dir().join(format!("storage_{}", std::process::id()));
+        let path = dir.join("store.json");
+
+        write_json(&path, &1).unwrap();
+        write_json(&path, &2).unwrap();
+        assert_eq!(read_json::<i32>(&path), Some(2));
+
+        std::fs::write(&path, b"{").unwrap();
+        assert_eq!(read_json::<i32>(&path), Some(1));
+
+        // Only the store and its backup are left
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,483 @@
+use crate::{
+    cache,
+    cues::{self, CueFormat, Timing},
+    menu::{self, PlayerMenu, PLAYER},
+    probe, settings, shell, storage,
+};
+use encoding_rs::{Encoding, SHIFT_JIS, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tauri::{AppHandle, Manager};
+
+const STORE_FILE_NAME: &str = "subtitles.json";
+const SUBTITLE_CACHE: &str = "subtitles";
+const SUBTITLE_EXTENSIONS: [&str; 4] = ["srt", "vtt", "ass", "ssa"];
+/// Picture based subtitles cannot be converted to text
+const BITMAP_CODECS: [&str; 4] = ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+pub const SUBTITLES_OFF: &str = "off";
+const SIDECAR_PREFIX: &str = "file:";
+const EMBEDDED_PREFIX: &str = "stream:";
+const MAX_OFFSET: f64 = 3600.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub enum SubtitleSource {
+    Sidecar {
+        path: String,
+    },
+    Embedded {
+        index: usize,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleTrack {
+    pub id: String,
+    pub source: SubtitleSource,
+    pub format: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleList {
+    pub tracks: Vec<SubtitleTrack>,
+    pub selected: Option<String>,
+    pub offset: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubtitleRequest {
+    pub filePath: String,
+    pub trackId: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubtitleOffset {
+    pub filePath: String,
+    pub offset: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubtitleExport {
+    pub filePath: String,
+    pub trackId: String,
+    /// Defaults to the offset saved for the file
+    pub timing: Option<Timing>,
+    pub file: String,
+}
+
+/// Per file key. A stored None selection means subtitles were turned off for the file.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct SubtitleStore {
+    #[serde(default)]
+    selections: BTreeMap<String, Option<String>>,
+    /// Seconds added to every cue, positive when the subtitles show too early
+    #[serde(default)]
+    offsets: BTreeMap<String, f64>,
+}
+
+#[derive(Default)]
+pub struct Subtitles {
+    store: SubtitleStore,
+    current: String,
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(STORE_FILE_NAME))
+}
+
+pub fn setup(app: &tauri::App) {
+    let store = store_path(app.handle()).ok().and_then(|path| storage::read_json(&path)).unwrap_or_default();
+    app.manage(Mutex::new(Subtitles {
+        store,
+        current: String::new(),
+    }));
+}
+
+fn modify<F>(app: &AppHandle, f: F) -> Result<(), String>
+where
+    F: FnOnce(&mut SubtitleStore),
+{
+    let state = app.state::<Mutex<Subtitles>>();
+    let mut subtitles = state.lock().unwrap();
+    f(&mut subtitles.store);
+    storage::write_json(&store_path(app)?, &subtitles.store)
+}
+
+/// Moves the remembered track and offset to the key the file has after a rename or an edit
+pub fn rekey(app: &AppHandle, old_key: &str, new_key: &str) -> Result<(), String> {
+    let known = {
+        let state = app.state::<Mutex<Subtitles>>();
+        let subtitles = state.lock().unwrap();
+        subtitles.store.selections.contains_key(old_key) || subtitles.store.offsets.contains_key(old_key)
+    };
+    if !known {
+        return Ok(());
+    }
+
+    modify(app, |store| {
+        if let Some(selection) = store.selections.remove(old_key) {
+            store.selections.insert(new_key.to_string(), selection);
+        }
+        if let Some(offset) = store.offsets.remove(old_key) {
+            store.offsets.insert(new_key.to_string(), offset);
+        }
+    })
+}
+
+fn offset_of(app: &AppHandle, key: &str) -> f64 {
+    app.state::<Mutex<Subtitles>>().lock().unwrap().store.offsets.get(key).copied().unwrap_or_default()
+}
+
+/// Finds "movie.srt", "movie.en.srt", "movie.en.forced.ass" and so on next to "movie.mkv"
+fn find_sidecars(file_path: &str) -> Vec<SubtitleTrack> {
+    let path = Path::new(file_path);
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy().to_lowercase();
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut tracks: Vec<SubtitleTrack> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|sidecar| {
+            let format = sidecar.extension()?.to_string_lossy().to_lowercase();
+            if !SUBTITLE_EXTENSIONS.contains(&format.as_str()) {
+                return None;
+            }
+
+            // Lowercasing can change the byte length, so the suffix is cut from the lowercased stem itself
+            let sidecar_stem = sidecar.file_stem()?.to_string_lossy().to_lowercase();
+            let suffix = if sidecar_stem == stem {
+                ""
+            } else {
+                sidecar_stem.strip_prefix(&stem)?.strip_prefix('.')?
+            };
+
+            let language = suffix.split('.').next().filter(|language| is_language_code(language)).map(str::to_string);
+            let name = sidecar.file_name()?.to_string_lossy().to_string();
+
+            Some(SubtitleTrack {
+                id: format!("{}{}", SIDECAR_PREFIX, name),
+                source: SubtitleSource::Sidecar {
+                    path: sidecar.to_string_lossy().to_string(),
+                },
+                format,
+                language,
+                title: Some(suffix.to_string()).filter(|suffix| !suffix.is_empty()),
+                default: false,
+            })
+        })
+        .collect();
+
+    tracks.sort_by(|a, b| a.id.cmp(&b.id));
+    tracks
+}
+
+/// Accepts "en", "eng" and region variants like "pt-BR"
+fn is_language_code(value: &str) -> bool {
+    let language = value.split(['-', '_']).next().unwrap_or_default();
+    (2..=3).contains(&language.len()) && language.chars().all(|char| char.is_ascii_alphabetic())
+}
+
+async fn find_embedded(app: &AppHandle, file_path: &str) -> Vec<SubtitleTrack> {
+    let Ok(probe) = probe::probe(app, file_path).await else {
+        return Vec::new();
+    };
+
+    probe
+        .streams
+        .iter()
+        .filter(|stream| stream.codec_type == "subtitle" && !BITMAP_CODECS.contains(&stream.codec_name.as_str()))
+        .map(|stream| SubtitleTrack {
+            id: format!("{}{}", EMBEDDED_PREFIX, stream.index),
+            source: SubtitleSource::Embedded {
+                index: stream.index,
+            },
+            format: stream.codec_name.clone(),
+            language: stream.tags.get("language").cloned(),
+            title: stream.tags.get("title").cloned(),
+            default: stream.disposition.get("default").is_some_and(|default| *default == 1),
+        })
+        .collect()
+}
+
+fn track_label(track: &SubtitleTrack) -> String {
+    let description = [track.language.as_deref(), track.title.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" - ");
+
+    let name = match &track.source {
+        SubtitleSource::Sidecar {
+            path,
+        } => Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+        SubtitleSource::Embedded {
+            index,
+        } => format!("#{} {}", index, track.format),
+    };
+
+    if description.is_empty() {
+        name
+    } else {
+        format!("{} ({})", name, description)
+    }
+}
+
+/// Lists sidecar and embedded tracks, restores the remembered choice and shows the tracks in the Subtitles submenu
+pub async fn list(app: &AppHandle, file_path: &str) -> Result<SubtitleList, String> {
+    let key = cache::file_key(file_path)?;
+    app.state::<Mutex<Subtitles>>().lock().unwrap().current = file_path.to_string();
+
+    let mut tracks = find_sidecars(file_path);
+    tracks.extend(find_embedded(app, file_path).await);
+
+    let remembered = app.state::<Mutex<Subtitles>>().lock().unwrap().store.selections.get(&key).cloned();
+    let selected = match remembered {
+        Some(selected) => selected.filter(|id| tracks.iter().any(|track| &track.id == id)),
+        None => tracks.iter().find(|track| track.default).map(|track| track.id.clone()),
+    };
+
+    if app.state::<Mutex<Subtitles>>().lock().unwrap().current == file_path {
+        let entries = tracks.iter().map(|track| (track.id.clone(), track_label(track))).collect();
+        let menu_selected = selected.clone().unwrap_or_else(|| SUBTITLES_OFF.to_string());
+        let app_handle = app.clone();
+        app.run_on_main_thread(move || {
+            menu::set_entries(&app_handle, PLAYER, PlayerMenu::Subtitles, entries, Some(menu_selected), &settings::get(&app_handle));
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(SubtitleList {
+        tracks,
+        selected,
+        offset: offset_of(app, &key),
+    })
+}
+
+/// Remembers the track chosen for the file, or that subtitles were turned off
+pub fn select(app: &AppHandle, request: SubtitleRequest) -> Result<(), String> {
+    let key = cache::file_key(&request.filePath)?;
+    let track_id = request.trackId.filter(|id| id != SUBTITLES_OFF);
+
+    modify(app, |store| {
+        store.selections.insert(key, track_id);
+    })
+}
+
+/// Saves the offset for the file and returns it rounded to milliseconds
+pub fn set_offset(app: &AppHandle, offset: SubtitleOffset) -> Result<f64, String> {
+    if !offset.offset.is_finite() || offset.offset.abs() > MAX_OFFSET {
+        return Err(format!("Invalid subtitle offset: {}", offset.offset));
+    }
+
+    let key = cache::file_key(&offset.filePath)?;
+    let value = (offset.offset * 1000.0).round() / 1000.0;
+
+    modify(app, |store| {
+        if value == 0.0 {
+            store.offsets.remove(&key);
+        } else {
+            store.offsets.insert(key, value);
+        }
+    })?;
+
+    Ok(value)
+}
+
+/// Returns the track as WebVTT text with the saved offset applied
+pub async fn load(app: &AppHandle, request: SubtitleRequest) -> Result<String, String> {
+    let offset = offset_of(app, &cache::file_key(&request.filePath)?);
+    let track_id = request.trackId.ok_or_else(|| String::from("No subtitle track"))?;
+    let vtt = load_track(app, &request.filePath, &track_id).await?;
+
+    if offset == 0.0 {
+        Ok(vtt)
+    } else {
+        Ok(cues::write(&cues::retime(cues::parse(&vtt), Timing::Offset(offset))?, CueFormat::Vtt))
+    }
+}
+
+/// Writes the track, re-timed, as SRT or WebVTT depending on the extension of the destination
+pub async fn export(app: &AppHandle, export: SubtitleExport) -> Result<(), String> {
+    let timing = match export.timing {
+        Some(timing) => timing,
+        None => Timing::Offset(offset_of(app, &cache::file_key(&export.filePath)?)),
+    };
+
+    let vtt = load_track(app, &export.filePath, &export.trackId).await?;
+    let cues = cues::retime(cues::parse(&vtt), timing)?;
+
+    storage::write_atomic(Path::new(&export.file), cues::write(&cues, CueFormat::from_extension(&export.file)).as_bytes())
+}
+
+async fn load_track(app: &AppHandle, file_path: &str, track_id: &str) -> Result<String, String> {
+    if track_id.starts_with(SIDECAR_PREFIX) {
+        let track = find_sidecars(file_path).into_iter().find(|track| track.id == track_id).ok_or_else(|| format!("Subtitle file not found: {}", track_id))?;
+        let SubtitleSource::Sidecar {
+            path,
+        } = track.source
+        else {
+            return Err(format!("Invalid subtitle track: {}", track_id));
+        };
+        return load_sidecar(app, Path::new(&path)).await;
+    }
+
+    if let Some(index) = track_id.strip_prefix(EMBEDDED_PREFIX) {
+        let index = index.parse::<usize>().map_err(|_| format!("Invalid subtitle track: {}", track_id))?;
+        return load_embedded(app, file_path, index).await;
+    }
+
+    Err(format!("Invalid subtitle track: {}", track_id))
+}
+
+async fn load_sidecar(app: &AppHandle, sidecar: &Path) -> Result<String, String> {
+    let data = std::fs::read(sidecar).map_err(|e| e.to_string())?;
+    let text = decode_text(&data);
+
+    match sidecar.extension().map(|extension| extension.to_string_lossy().to_lowercase()).as_deref() {
+        Some("srt") => Ok(srt_to_vtt(&text)),
+        Some("vtt") => Ok(text),
+        _ => ffmpeg_to_vtt(app, &sidecar.to_string_lossy(), None).await,
+    }
+}
+
+/// Embedded tracks need a full pass over the file, so the converted text is cached
+async fn load_embedded(app: &AppHandle, file_path: &str, index: usize) -> Result<String, String> {
+    let extension = format!("{}.vtt", index);
+    if let Some(data) = cache::read(app, SUBTITLE_CACHE, file_path, &extension) {
+        return Ok(decode_text(&data));
+    }
+
+    let probe = probe::probe(app, file_path).await?;
+    let stream = probe.streams.iter().find(|stream| stream.index == index && stream.codec_type == "subtitle").ok_or_else(|| format!("Subtitle stream {} not found", index))?;
+
+    let vtt = if stream.codec_name == "subrip" {
+        let map = format!("0:{}", index);
+        let args = ["-hide_banner", "-v", "error", "-i", file_path, "-map", &map, "-c:s", "copy", "-f", "srt", "-"];
+        let output = shell::exec(app, "ffmpeg", args.iter().map(|arg| arg.to_string()).collect()).await?;
+        srt_to_vtt(&output.stdout)
+    } else {
+        ffmpeg_to_vtt(app, file_path, Some(index)).await?
+    };
+
+    let _ = cache::write(app, SUBTITLE_CACHE, file_path, &extension, vtt.as_bytes());
+
+    Ok(vtt)
+}
+
+async fn ffmpeg_to_vtt(app: &AppHandle, input: &str, index: Option<usize>) -> Result<String, String> {
+    let mut args = vec!["-hide_banner".to_string(), "-v".to_string(), "error".to_string(), "-i".to_string(), input.to_string()];
+    if let Some(index) = index {
+        args.extend(["-map".to_string(), format!("0:{}", index)]);
+    }
+    args.extend(["-f".to_string(), "webvtt".to_string(), "-".to_string()]);
+
+    let output = shell::exec(app, "ffmpeg", args).await?;
+    Ok(output.stdout)
+}
+
+/// Decodes by the BOM when there is one, then as UTF-8, then as Shift_JIS, which most legacy Japanese subtitles use.
+/// Text that is not valid Shift_JIS either is read as Windows-1252, which accepts any byte.
+pub fn decode_text(data: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(data) {
+        return encoding.decode_without_bom_handling(&data[bom_length..]).0.into_owned();
+    }
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        return text.to_string();
+    }
+
+    match SHIFT_JIS.decode_without_bom_handling_and_without_replacement(data) {
+        Some(text) => text.into_owned(),
+        None => WINDOWS_1252.decode_without_bom_handling(data).0.into_owned(),
+    }
+}
+
+fn srt_to_vtt(srt: &str) -> String {
+    cues::write(&cues::parse(srt), CueFormat::Vtt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_by_bom_then_utf8_then_shift_jis() {
+        assert_eq!(decode_text("\u{FEFF}字幕".as_bytes()), "字幕");
+        assert_eq!(decode_text("字幕".as_bytes()), "字幕");
+        assert_eq!(decode_text(&[0xFF, 0xFE, 0x57, 0x5B, 0x55, 0x5E]), "字幕");
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0x5B, 0x57, 0x5E, 0x55]), "字幕");
+        assert_eq!(decode_text(&[0x8E, 0x9A, 0x96, 0x8B]), "字幕");
+        assert_eq!(decode_text(&[0x63, 0x61, 0x66, 0xE9]), "caf\u{E9}");
+    }
+
+    #[test]
+    fn language_codes_allow_regions() {
+        for code in ["en", "eng", "pt-BR", "zh_Hans"] {
+            assert!(is_language_code(code), "{}", code);
+        }
+        for value in ["", "e", "english", "forced", "e1", "-en"] {
+            assert!(!is_language_code(value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn labels_show_the_source_and_description() {
+        let mut track = SubtitleTrack {
+            id: String::new(),
+            source: SubtitleSource::Sidecar {
+                path: String::from("/movies/movie.en.srt"),
+            },
+            format: String::from("srt"),
+            language: Some(String::from("en")),
+            title: Some(String::from("SDH")),
+            default: false,
+        };
+        assert_eq!(track_label(&track), "movie.en.srt (en - SDH)");
+
+        track.source = SubtitleSource::Embedded {
+            index: 2,
+        };
+        track.format = String::from("subrip");
+        track.title = None;
+        assert_eq!(track_label(&track), "#2 subrip (en)");
+
+        track.language = None;
+        assert_eq!(track_label(&track), "#2 subrip");
+    }
+
+    #[test]
+    fn srt_converts_to_vtt() {
+        let vtt = srt_to_vtt("1\r\n00:00:01,000 --> 00:00:02,500\r\nHello\r\n\r\n2\r\n00:01:00,250 --> 00:01:01,000\r\n<i>World</i>\r\n");
+
+        assert_eq!(vtt, "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nHello\n\n00:01:00.250 --> 00:01:01.000\n<i>World</i>\n\n");
+    }
+
+    #[test]
+    fn finds_sidecars_whatever_the_case() {
+        let dir = std::env::temp_dir().join(format!("subtitles_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // "İ" takes more bytes once lowercased
+        let movie = dir.join("İstanbul.mkv");
+        for name in ["İstanbul.mkv", "İSTANBUL.srt", "İSTANBUL.EN.Forced.ass", "istanbul.ja.vtt", "İstanbulx.srt", "İstanbul.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let tracks = find_sidecars(&movie.to_string_lossy());
+        let found: Vec<(String, Option<&str>, Option<&str>)> = tracks.iter().map(|track| (track.id.replace(SIDECAR_PREFIX, ""), track.language.as_deref(), track.title.as_deref())).collect();
+        assert_eq!(found, [(String::from("İSTANBUL.EN.Forced.ass"), Some("en"), Some("en.forced")), (String::from("İSTANBUL.srt"), None, None),]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,403 @@
+use crate::probe::{self, Probe};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const MAX_NAME_BYTES: usize = 255;
+#[cfg(target_os = "windows")]
+const RESERVED_NAMES: [&str; 22] =
+    ["CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagRenameOptions {
+    files: Vec<String>,
+    template: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TagRenameStatus {
+    Ready,
+    Unchanged,
+    MissingTags,
+    Exists,
+    Duplicate,
+    ProbeFailed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRenameEntry {
+    pub old: String,
+    pub new: String,
+    pub status: TagRenameStatus,
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRenameFailure {
+    pub old: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRenameResult {
+    pub renamed: Vec<TagRenameEntry>,
+    pub failed: Vec<TagRenameFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRenameReport {
+    pub entries: Vec<TagRenameEntry>,
+    pub collisions: usize,
+}
+
+enum Token {
+    Literal(String),
+    Field {
+        name: String,
+        width: usize,
+    },
+}
+
+fn parse_template(template: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            literal.push(c);
+            continue;
+        }
+
+        let mut field = String::new();
+        let mut closed = false;
+        for c in chars.by_ref() {
+            if c == '}' {
+                closed = true;
+                break;
+            }
+            field.push(c);
+        }
+
+        if !closed {
+            return Err(format!("Unclosed placeholder in template: {}", template));
+        }
+        if field.is_empty() {
+            return Err(format!("Empty placeholder in template: {}", template));
+        }
+
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(std::mem::take(&mut literal)));
+        }
+
+        let (name, width) = match field.split_once(':') {
+            Some((name, width)) => (name, width.parse::<usize>().map_err(|_| format!("Invalid width in placeholder: {{{}}}", field))?),
+            None => (field.as_str(), 0),
+        };
+        tokens.push(Token::Field {
+            name: name.trim().to_lowercase(),
+            width,
+        });
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    Ok(tokens)
+}
+
+fn field_value(probe: &Probe, source: &Path, name: &str) -> Option<String> {
+    match name {
+        "track" | "disc" => probe.tag(name).and_then(|value| value.split('/').next().map(|number| number.trim().to_string())).filter(|number| !number.is_empty()),
+        "year" => probe.tag("date").map(|date| date.chars().take(4).collect()),
+        "artist" => probe.tag("artist").or_else(|| probe.tag("album_artist")),
+        "name" => source.file_stem().map(|stem| stem.to_string_lossy().to_string()),
+        _ => probe.tag(name),
+    }
+}
+
+fn render(tokens: &[Token], probe: &Probe, source: &Path, missing: &mut Vec<String>) -> String {
+    let mut name = String::new();
+
+    for token in tokens {
+        match token {
+            Token::Literal(literal) => name.push_str(literal),
+            Token::Field {
+                name: field,
+                width,
+            } => match field_value(probe, source, field) {
+                Some(value) if value.parse::<u64>().is_ok() => name.push_str(&format!("{:0>width$}", value, width = *width)),
+                Some(value) => name.push_str(&value),
+                None => missing.push(field.clone()),
+            },
+        }
+    }
+
+    name
+}
+
+/// Makes a file stem safe for the filesystem this build targets
+pub fn sanitize(name: &str) -> String {
+    #[cfg(target_os = "windows")]
+    let invalid: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+    #[cfg(not(target_os = "windows"))]
+    let invalid: &[char] = &['/'];
+
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || invalid.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    #[cfg(target_os = "windows")]
+    {
+        sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+        let upper = sanitized.split('.').next().unwrap_or_default().to_uppercase();
+        if RESERVED_NAMES.contains(&upper.as_str()) {
+            sanitized.push('_');
+        }
+    }
+
+    sanitized = sanitized.trim().to_string();
+    if sanitized == "." || sanitized == ".." {
+        sanitized = sanitized.replace('.', "_");
+    }
+
+    sanitized
+}
+
+fn truncate(stem: &str, extension: &str) -> String {
+    let mut stem = stem.to_string();
+    while !stem.is_empty() && stem.len() + extension.len() > MAX_NAME_BYTES {
+        stem.pop();
+    }
+    format!("{}{}", stem, extension)
+}
+
+pub async fn preview(app: &tauri::AppHandle, options: TagRenameOptions) -> Result<TagRenameReport, String> {
+    let tokens = parse_template(&options.template)?;
+    let mut entries = Vec::new();
+
+    for file in options.files {
+        let source = PathBuf::from(&file);
+        let probe = match probe::probe(app, &file).await {
+            Ok(probe) => probe,
+            Err(_) => {
+                entries.push(TagRenameEntry {
+                    old: file,
+                    new: String::new(),
+                    status: TagRenameStatus::ProbeFailed,
+                    missing: Vec::new(),
+                });
+                continue;
+            }
+        };
+
+        let mut missing = Vec::new();
+        let stem = sanitize(&render(&tokens, &probe, &source, &mut missing));
+        let extension = source.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+        let new = source.with_file_name(truncate(&stem, &extension)).to_string_lossy().to_string();
+        let status = if !missing.is_empty() || stem.is_empty() {
+            TagRenameStatus::MissingTags
+        } else if new == file {
+            TagRenameStatus::Unchanged
+        } else {
+            TagRenameStatus::Ready
+        };
+
+        entries.push(TagRenameEntry {
+            old: file,
+            new,
+            status,
+            missing,
+        });
+    }
+
+    Ok(report(entries))
+}
+
+fn report(mut entries: Vec<TagRenameEntry>) -> TagRenameReport {
+    let mut targets: HashMap<String, usize> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.status == TagRenameStatus::Ready) {
+        *targets.entry(normalize(&entry.new)).or_default() += 1;
+    }
+
+    for entry in entries.iter_mut().filter(|entry| entry.status == TagRenameStatus::Ready) {
+        if targets.get(&normalize(&entry.new)).copied().unwrap_or_default() > 1 {
+            entry.status = TagRenameStatus::Duplicate;
+        } else if Path::new(&entry.new).exists() && normalize(&entry.new) != normalize(&entry.old) {
+            entry.status = TagRenameStatus::Exists;
+        }
+    }
+
+    let collisions = entries.iter().filter(|entry| matches!(entry.status, TagRenameStatus::Exists | TagRenameStatus::Duplicate)).count();
+
+    TagRenameReport {
+        entries,
+        collisions,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn normalize(path: &str) -> String {
+    path.to_lowercase()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn normalize(path: &str) -> String {
+    path.to_string()
+}
+
+/// Renames the entries that were reported as ready. A failure, including a target that appeared since the preview, is reported per entry and does not stop the rest.
+pub fn apply(entries: Vec<TagRenameEntry>) -> TagRenameResult {
+    let mut renamed = Vec::new();
+    let mut failed = Vec::new();
+
+    for entry in entries.into_iter().filter(|entry| entry.status == TagRenameStatus::Ready) {
+        match rename_no_replace(&entry.old, &entry.new) {
+            Ok(_) => renamed.push(entry),
+            Err(error) => failed.push(TagRenameFailure {
+                old: entry.old,
+                error,
+            }),
+        }
+    }
+
+    TagRenameResult {
+        renamed,
+        failed,
+    }
+}
+
+/// Renames without replacing a file at the target, even one created after the check.
+/// A hard link fails when the target exists, so linking and then removing the old name cannot clobber anything.
+/// Filesystems without hard links fall back to a check followed by a rename.
+fn rename_no_replace(old: &str, new: &str) -> Result<(), String> {
+    // A change of case only names the same file on case-insensitive filesystems
+    if normalize(old) == normalize(new) {
+        return std::fs::rename(old, new).map_err(|e| e.to_string());
+    }
+
+    match std::fs::hard_link(old, new) {
+        Ok(_) => std::fs::remove_file(old).map_err(|e| {
+            let _ = std::fs::remove_file(new);
+            e.to_string()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(format!("File exists: {}", new)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(e.to_string()),
+        Err(_) => {
+            if Path::new(new).exists() {
+                return Err(format!("File exists: {}", new));
+            }
+            std::fs::rename(old, new).map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(old: &std::path::Path, new: &std::path::Path) -> TagRenameEntry {
+        TagRenameEntry {
+            old: old.to_string_lossy().to_string(),
+            new: new.to_string_lossy().to_string(),
+            status: TagRenameStatus::Ready,
+            missing: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_fields_and_literals() {
+        let tokens = parse_template("{Track:2} - {title}").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(&tokens[0], Token::Field { name, width: 2 } if name == "track"));
+        assert!(matches!(&tokens[1], Token::Literal(literal) if literal == " - "));
+        assert!(matches!(&tokens[2], Token::Field { name, width: 0 } if name == "title"));
+    }
+
+    #[test]
+    fn rejects_broken_placeholders() {
+        assert!(parse_template("{title").is_err());
+        assert!(parse_template("{track} {").is_err());
+        assert!(parse_template("{}").is_err());
+        assert!(parse_template("{track:x}").is_err());
+    }
+
+    #[test]
+    fn sanitizes_and_truncates_names() {
+        assert_eq!(sanitize(" a/b "), "a_b");
+        assert_eq!(sanitize(".."), "__");
+        let name = truncate(&"a".repeat(300), ".flac");
+        assert_eq!(name.len(), MAX_NAME_BYTES);
+        assert!(name.ends_with(".flac"));
+    }
+
+    #[test]
+    fn report_marks_duplicate_and_existing_targets() {
+        let dir = std::env::temp_dir().join(format!("tag_rename_report_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("taken.mp3"), b"taken").unwrap();
+
+        let mut unchanged = entry(&dir.join("d.mp3"), &dir.join("d.mp3"));
+        unchanged.status = TagRenameStatus::Unchanged;
+        let report = report(vec![
+            entry(&dir.join("a.mp3"), &dir.join("same.mp3")),
+            entry(&dir.join("b.mp3"), &dir.join("same.mp3")),
+            entry(&dir.join("c.mp3"), &dir.join("taken.mp3")),
+            entry(&dir.join("e.mp3"), &dir.join("free.mp3")),
+            unchanged,
+        ]);
+
+        let statuses: Vec<TagRenameStatus> = report.entries.iter().map(|entry| entry.status.clone()).collect();
+        assert_eq!(statuses, [TagRenameStatus::Duplicate, TagRenameStatus::Duplicate, TagRenameStatus::Exists, TagRenameStatus::Ready, TagRenameStatus::Unchanged]);
+        assert_eq!(report.collisions, 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_never_replaces_an_existing_file() {
+        let dir = std::env::temp_dir().join(format!("tag_rename_replace_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.mp3"), dir.join("b.mp3"));
+        std::fs::write(&a, b"a").unwrap();
+        // Created after the preview reported the target as free
+        std::fs::write(&b, b"b").unwrap();
+
+        let result = apply(vec![entry(&a, &b)]);
+
+        assert!(result.renamed.is_empty());
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(std::fs::read(&a).unwrap(), b"a");
+        assert_eq!(std::fs::read(&b).unwrap(), b"b");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_reports_failures_and_keeps_going() {
+        let dir = std::env::temp_dir().join(format!("tag_rename_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b, c) = (dir.join("a.mp3"), dir.join("b.mp3"), dir.join("c.mp3"));
+        std::fs::write(&a, b"a").unwrap();
+        std::fs::write(&b, b"b").unwrap();
+
+        let result = apply(vec![entry(&dir.join("missing.mp3"), &dir.join("x.mp3")), entry(&a, &b), entry(&b, &c)]);
+
+        assert_eq!(result.renamed.len(), 1);
+        assert_eq!(result.renamed[0].new, c.to_string_lossy());
+        assert_eq!(result.failed.len(), 2);
+        assert!(a.exists() && c.exists() && !b.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    cache,
+    probe::{self, Probe},
+    shell, storage,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tauri::AppHandle;
+
+const EDITABLE_TAGS: [&str; 7] = ["title", "artist", "album", "album_artist", "date", "comment", "track"];
+/// Containers where ffmpeg can store a cover as an attached picture stream
+const COVER_ART_EXTENSIONS: [&str; 7] = ["mp3", "mp4", "m4a", "m4v", "mov", "flac", "3gp"];
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagEdit {
+    pub filePath: String,
+    /// Tags to write. An empty value removes the tag.
+    pub tags: BTreeMap<String, String>,
+    /// Image that replaces the current cover
+    pub coverArt: Option<String>,
+    /// Keeps the original file as "<name>.bak"
+    pub backup: bool,
+}
+
+/// Current values of the editable tags
+pub async fn read(app: &AppHandle, file_path: &str) -> Result<BTreeMap<String, String>, String> {
+    let probe = probe::probe(app, file_path).await?;
+    Ok(EDITABLE_TAGS.iter().map(|key| (key.to_string(), probe.tag(key).unwrap_or_default())).collect())
+}
+
+/// Remuxes the file with the new tags into a temporary file, checks the result and replaces the original with it
+pub async fn write(app: &AppHandle, edit: TagEdit) -> Result<Probe, String> {
+    if let Some(key) = edit.tags.keys().find(|key| !EDITABLE_TAGS.contains(&key.as_str())) {
+        return Err(format!("Unsupported tag: {}", key));
+    }
+
+    let path = Path::new(&edit.filePath);
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+
+    if edit.coverArt.is_some() && !COVER_ART_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!("Cover art cannot be stored in .{} files", extension));
+    }
+
+    // The new size and modification time change the key, so what is remembered for the file is moved over
+    let key = cache::file_key(path)?;
+    let original = probe::probe(app, &edit.filePath).await?;
+    let temp = temp_path(path);
+    let temp_str = temp.to_string_lossy().to_string();
+
+    let result = match remux(app, &edit, &original, &extension, &temp_str).await {
+        Ok(()) => verify(app, &edit, &original, &temp_str).await,
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    replace(path, &temp, edit.backup)?;
+    cache::rekey(app, &key, &edit.filePath);
+
+    probe::probe(app, &edit.filePath).await
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    // ffmpeg picks the muxer from the extension, so it has to stay last
+    match path.extension() {
+        Some(extension) => path.with_file_name(format!("{}.tagging.{}", stem, extension.to_string_lossy())),
+        None => path.with_file_name(format!("{}.tagging", stem)),
+    }
+}
+
+async fn remux(app: &AppHandle, edit: &TagEdit, original: &Probe, extension: &str, temp: &str) -> Result<(), String> {
+    let mut args: Vec<String> = ["-hide_banner", "-v", "error", "-i", &edit.filePath].iter().map(|arg| arg.to_string()).collect();
+
+    match &edit.coverArt {
+        Some(cover_art) => {
+            args.extend(["-i".to_string(), cover_art.clone()]);
+
+            // Drops the current cover so that the file does not end up with two
+            let kept: Vec<usize> = original.streams.iter().filter(|stream| !stream.is_cover()).map(|stream| stream.index).collect();
+            for index in &kept {
+                args.extend(["-map".to_string(), format!("0:{}", index)]);
+            }
+            args.extend(["-map".to_string(), "1:0".to_string(), format!("-disposition:{}", kept.len()), "attached_pic".to_string()]);
+        }
+        None => args.extend(["-map".to_string(), "0".to_string()]),
+    }
+
+    args.extend(["-map_metadata".to_string(), "0".to_string(), "-c".to_string(), "copy".to_string()]);
+
+    for (key, value) in &edit.tags {
+        args.extend(["-metadata".to_string(), format!("{}={}", key, value.trim())]);
+    }
+
+    if extension == "mp3" {
+        // ID3v2.3 is the version most tag readers understand
+        args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+    }
+
+    args.extend(["-y".to_string(), temp.to_string()]);
+
+    shell::exec(app, "ffmpeg", args).await.map(|_| ())
+}
+
+async fn verify(app: &AppHandle, edit: &TagEdit, original: &Probe, temp: &str) -> Result<(), String> {
+    let result = probe::probe_uncached(app, temp).await?;
+
+    let media_streams = |probe: &Probe| probe.streams.iter().filter(|stream| !stream.is_cover()).count();
+    if media_streams(&result) != media_streams(original) {
+        return Err(String::from("The rewritten file lost streams"));
+    }
+
+    if edit.coverArt.is_some() && !result.streams.iter().any(probe::Stream::is_cover) {
+        return Err(String::from("The cover art was not stored"));
+    }
+
+    for (key, value) in &edit.tags {
+        let value = value.trim();
+        let written = result.tag(key).unwrap_or_default();
+        // Containers differ in whether they keep the "/total" part of a track number
+        let stored = if key == "track" {
+            written.split('/').next() == value.split('/').next()
+        } else {
+            written == value
+        };
+        if !stored {
+            return Err(format!("The {} tag was not stored as \"{}\"", key, value));
+        }
+    }
+
+    Ok(())
+}
+
+fn replace(path: &Path, temp: &Path, backup: bool) -> Result<(), String> {
+    if !backup {
+        return std::fs::rename(temp, path).map_err(|e| {
+            let _ = std::fs::remove_file(temp);
+            e.to_string()
+        });
+    }
+
+    let backup_path = storage::backup_path(path);
+    std::fs::rename(path, &backup_path).map_err(|e| {
+        let _ = std::fs::remove_file(temp);
+        e.to_string()
+    })?;
+
+    std::fs::rename(temp, path).map_err(|e| {
+        let _ = std::fs::rename(&backup_path, path);
+        let _ = std::fs::remove_file(temp);
+        e.to_string()
+    })
+}
@@ -0,0 +1,565 @@
+use crate::{cache, journal::MovedFile, storage, trash};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{ipc::Channel, AppHandle, Manager};
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const ANSWER_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    Skip,
+    Overwrite,
+    Rename,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ConflictAnswer {
+    Skip,
+    Overwrite,
+    Rename,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum TransferEvent {
+    Started {
+        total_files: usize,
+        total_bytes: u64,
+    },
+    FileStarted {
+        index: usize,
+        from: String,
+        to: String,
+        size: u64,
+    },
+    Progress {
+        index: usize,
+        file_bytes: u64,
+        file_size: u64,
+        transferred_bytes: u64,
+        total_bytes: u64,
+    },
+    Conflict {
+        index: usize,
+        from: String,
+        to: String,
+    },
+    FileFinished {
+        index: usize,
+        from: String,
+        to: String,
+        skipped: bool,
+    },
+    Finished {
+        cancelled: bool,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct TransferInfo {
+    from: Vec<String>,
+    to: String,
+    #[serde(default)]
+    policy: ConflictPolicy,
+    #[serde(default)]
+    cancellation_token: Option<String>,
+    #[serde(default)]
+    on_progress: Option<Channel<TransferEvent>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConflictAnswerInfo {
+    cancellation_token: String,
+    answer: ConflictAnswer,
+    #[serde(default)]
+    apply_to_all: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferFailure {
+    pub file: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TransferResult {
+    pub completed: Vec<MovedFile>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<TransferFailure>,
+    pub cancelled: bool,
+}
+
+enum Outcome {
+    /// Destination, and whether a file there was moved to the trash
+    Done(String, bool),
+    Skipped,
+    Cancelled,
+}
+
+struct TransferControl {
+    cancelled: Arc<AtomicBool>,
+    answer: Sender<(ConflictAnswer, bool)>,
+}
+
+#[derive(Default)]
+pub struct Transfers(HashMap<String, TransferControl>);
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Mutex::new(Transfers::default()));
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Copy,
+    Move,
+}
+
+struct Job {
+    mode: Mode,
+    policy: ConflictPolicy,
+    cancelled: Arc<AtomicBool>,
+    answers: Receiver<(ConflictAnswer, bool)>,
+    channel: Option<Channel<TransferEvent>>,
+    transferred_bytes: u64,
+    total_bytes: u64,
+    last_progress: Instant,
+}
+
+pub async fn copy_all(app: &AppHandle, info: TransferInfo) -> Result<TransferResult, String> {
+    run(app, info, Mode::Copy).await
+}
+
+/// Moves the files along with what is remembered for them, since a copy between volumes does not keep the modification time in the key
+pub async fn mv_all(app: &AppHandle, info: TransferInfo) -> Result<TransferResult, String> {
+    let keys = cache::file_keys(info.from.iter().map(String::as_str));
+    let result = run(app, info, Mode::Move).await?;
+    cache::rekey_moved(app, &keys, &result.completed);
+    Ok(result)
+}
+
+pub fn cancel(app: &AppHandle, cancellation_token: &str) {
+    let state = app.state::<Mutex<Transfers>>();
+    let transfers = state.lock().unwrap();
+    if let Some(control) = transfers.0.get(cancellation_token) {
+        control.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn answer_conflict(app: &AppHandle, info: ConflictAnswerInfo) -> Result<(), String> {
+    let state = app.state::<Mutex<Transfers>>();
+    let transfers = state.lock().unwrap();
+    let control = transfers.0.get(&info.cancellation_token).ok_or("No transfer is waiting for an answer")?;
+    control.answer.send((info.answer, info.apply_to_all)).map_err(|e| e.to_string())
+}
+
+async fn run(app: &AppHandle, info: TransferInfo, mode: Mode) -> Result<TransferResult, String> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, answers) = channel();
+    let token = info.cancellation_token.clone();
+
+    if let Some(token) = &token {
+        let state = app.state::<Mutex<Transfers>>();
+        state.lock().unwrap().0.insert(
+            token.clone(),
+            TransferControl {
+                cancelled: cancelled.clone(),
+                answer: sender,
+            },
+        );
+    }
+
+    let job = Job {
+        mode,
+        policy: info.policy,
+        cancelled,
+        answers,
+        channel: info.on_progress,
+        transferred_bytes: 0,
+        total_bytes: 0,
+        last_progress: Instant::now(),
+    };
+
+    let result = tauri::async_runtime::spawn_blocking(move || transfer(job, info.from, PathBuf::from(info.to))).await.map_err(|e| e.to_string());
+
+    if let Some(token) = &token {
+        let state = app.state::<Mutex<Transfers>>();
+        state.lock().unwrap().0.remove(token);
+    }
+
+    result?
+}
+
+fn transfer(mut job: Job, from: Vec<String>, dest_dir: PathBuf) -> Result<TransferResult, String> {
+    if !dest_dir.is_dir() {
+        return Err(format!("{} is not a directory", dest_dir.to_string_lossy()));
+    }
+
+    job.total_bytes = from.iter().filter_map(|file| std::fs::metadata(file).ok()).map(|metadata| metadata.len()).sum();
+    job.send(TransferEvent::Started {
+        total_files: from.len(),
+        total_bytes: job.total_bytes,
+    });
+
+    let mut result = TransferResult::default();
+
+    for (index, source) in from.iter().enumerate() {
+        if job.is_cancelled() {
+            result.cancelled = true;
+            break;
+        }
+
+        match job.transfer_entry(index, source, &dest_dir) {
+            Ok(Outcome::Done(dest, replaced)) => result.completed.push(MovedFile {
+                from: source.clone(),
+                to: dest,
+                replaced,
+            }),
+            Ok(Outcome::Skipped) => result.skipped.push(source.clone()),
+            Ok(Outcome::Cancelled) => {
+                result.cancelled = true;
+                break;
+            }
+            Err(error) => result.failed.push(TransferFailure {
+                file: source.clone(),
+                error,
+            }),
+        }
+    }
+
+    job.send(TransferEvent::Finished {
+        cancelled: result.cancelled,
+    });
+
+    Ok(result)
+}
+
+impl Job {
+    fn transfer_entry(&mut self, index: usize, source: &str, dest_dir: &Path) -> Result<Outcome, String> {
+        let source_path = PathBuf::from(source);
+        let file_name = source_path.file_name().ok_or(format!("Invalid file path: {}", source))?;
+        let size = std::fs::metadata(&source_path).map_err(|e| e.to_string())?.len();
+        let mut dest = dest_dir.join(file_name);
+        let mut replace = false;
+
+        if dest.exists() {
+            let answer = if dest == source_path {
+                ConflictAnswer::Skip
+            } else {
+                self.resolve_conflict(index, source, &dest)
+            };
+
+            match answer {
+                ConflictAnswer::Skip => {
+                    self.transferred_bytes += size;
+                    self.finish_file(index, source, &dest, true);
+                    return Ok(Outcome::Skipped);
+                }
+                ConflictAnswer::Overwrite => replace = true,
+                ConflictAnswer::Rename => dest = with_suffix(&dest),
+                ConflictAnswer::Cancel => return Ok(Outcome::Cancelled),
+            }
+        }
+
+        self.send(TransferEvent::FileStarted {
+            index,
+            from: source.to_string(),
+            to: dest.to_string_lossy().to_string(),
+            size,
+        });
+
+        if !self.transfer_file(index, &source_path, &dest, size, replace)? {
+            return Ok(Outcome::Cancelled);
+        }
+
+        self.finish_file(index, source, &dest, false);
+        Ok(Outcome::Done(dest.to_string_lossy().to_string(), replace && self.mode == Mode::Move))
+    }
+
+    fn send(&self, event: TransferEvent) {
+        if let Some(channel) = &self.channel {
+            let _ = channel.send(event);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn finish_file(&self, index: usize, from: &str, to: &Path, skipped: bool) {
+        self.send(TransferEvent::FileFinished {
+            index,
+            from: from.to_string(),
+            to: to.to_string_lossy().to_string(),
+            skipped,
+        });
+    }
+
+    fn resolve_conflict(&mut self, index: usize, from: &str, to: &Path) -> ConflictAnswer {
+        match self.policy {
+            ConflictPolicy::Skip => ConflictAnswer::Skip,
+            ConflictPolicy::Overwrite => ConflictAnswer::Overwrite,
+            ConflictPolicy::Rename => ConflictAnswer::Rename,
+            ConflictPolicy::Ask => {
+                self.send(TransferEvent::Conflict {
+                    index,
+                    from: from.to_string(),
+                    to: to.to_string_lossy().to_string(),
+                });
+
+                let (answer, apply_to_all) = self.wait_for_answer();
+                if apply_to_all {
+                    self.policy = match answer {
+                        ConflictAnswer::Overwrite => ConflictPolicy::Overwrite,
+                        ConflictAnswer::Rename => ConflictPolicy::Rename,
+                        _ => ConflictPolicy::Skip,
+                    };
+                }
+                answer
+            }
+        }
+    }
+
+    fn wait_for_answer(&self) -> (ConflictAnswer, bool) {
+        // Without a channel nobody can answer, so fall back to the non-destructive choice
+        if self.channel.is_none() {
+            return (ConflictAnswer::Skip, false);
+        }
+
+        loop {
+            match self.answers.recv_timeout(ANSWER_POLL_INTERVAL) {
+                Ok(answer) => return answer,
+                Err(RecvTimeoutError::Timeout) if !self.is_cancelled() => continue,
+                _ => return (ConflictAnswer::Cancel, false),
+            }
+        }
+    }
+
+    /// Returns false when the transfer was cancelled part way through the file.
+    /// An existing destination is only replaced once the new file is complete.
+    /// A move can be undone, so the file it replaces goes to the trash, from where undo restores it.
+    fn transfer_file(&mut self, index: usize, source: &Path, dest: &Path, size: u64, replace: bool) -> Result<bool, String> {
+        let trash_replaced = self.mode == Mode::Move && replace;
+        let partial = storage::temp_path(dest);
+
+        if self.mode == Mode::Move {
+            // Renaming replaces the destination in one step on the same volume, so a destination to trash is renamed next to first
+            let renamed = if trash_replaced {
+                std::fs::rename(source, &partial).is_ok()
+            } else {
+                std::fs::rename(source, dest).is_ok()
+            };
+
+            if renamed {
+                if trash_replaced {
+                    if let Err(e) = replace_by_trash(&partial, dest) {
+                        let _ = std::fs::rename(&partial, source);
+                        return Err(e);
+                    }
+                }
+                self.transferred_bytes += size;
+                self.progress(index, size, size, true);
+                return Ok(true);
+            }
+        }
+
+        let copied = self.copy_file(index, source, &partial, size).and_then(|completed| {
+            if completed {
+                if trash_replaced {
+                    replace_by_trash(&partial, dest)?;
+                } else {
+                    std::fs::rename(&partial, dest).map_err(|e| e.to_string())?;
+                }
+            }
+            Ok(completed)
+        });
+
+        if !matches!(copied, Ok(true)) {
+            let _ = std::fs::remove_file(&partial);
+            return copied;
+        }
+
+        if self.mode == Mode::Move {
+            std::fs::remove_file(source).map_err(|e| e.to_string())?;
+        }
+
+        Ok(true)
+    }
+
+    fn copy_file(&mut self, index: usize, source: &Path, dest: &Path, size: u64) -> Result<bool, String> {
+        let mut reader = File::open(source).map_err(|e| e.to_string())?;
+        let mut writer = File::create(dest).map_err(|e| e.to_string())?;
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut file_bytes = 0;
+
+        loop {
+            if self.is_cancelled() {
+                return Ok(false);
+            }
+
+            let read = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+
+            writer.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+            file_bytes += read as u64;
+            self.transferred_bytes += read as u64;
+            self.progress(index, file_bytes, size, false);
+        }
+
+        writer.flush().map_err(|e| e.to_string())?;
+
+        let metadata = reader.metadata().map_err(|e| e.to_string())?;
+        if let Ok(modified) = metadata.modified() {
+            let _ = writer.set_modified(modified);
+        }
+        let _ = std::fs::set_permissions(dest, metadata.permissions());
+
+        self.progress(index, file_bytes, size, true);
+
+        Ok(true)
+    }
+
+    fn progress(&mut self, index: usize, file_bytes: u64, file_size: u64, force: bool) {
+        if !force && self.last_progress.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+
+        self.last_progress = Instant::now();
+        self.send(TransferEvent::Progress {
+            index,
+            file_bytes,
+            file_size,
+            transferred_bytes: self.transferred_bytes,
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+/// Moves the destination to the trash and puts the complete file in its place
+fn replace_by_trash(complete: &Path, dest: &Path) -> Result<(), String> {
+    trash::trash(&dest.to_string_lossy())?;
+    std::fs::rename(complete, dest).map_err(|e| e.to_string())
+}
+
+/// Appends " (n)" to the file stem until the name is free
+fn with_suffix(dest: &Path) -> PathBuf {
+    let stem = dest.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = dest.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    let mut count = 1;
+    loop {
+        let candidate = dest.with_file_name(format!("{} ({}){}", stem, count, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(mode: Mode, policy: ConflictPolicy) -> Job {
+        Job {
+            mode,
+            policy,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            answers: channel().1,
+            channel: None,
+            transferred_bytes: 0,
+            total_bytes: 0,
+            last_progress: Instant::now(),
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("transfer_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("dest")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn overwrite_replaces_the_destination() {
+        let dir = test_dir("overwrite");
+        let source = dir.join("a.mp4");
+        std::fs::write(&source, b"new").unwrap();
+        std::fs::write(dir.join("dest").join("a.mp4"), b"old").unwrap();
+
+        let result = transfer(job(Mode::Copy, ConflictPolicy::Overwrite), vec![source.to_string_lossy().to_string()], dir.join("dest")).unwrap();
+
+        assert_eq!(result.completed.len(), 1);
+        // Only moves are journaled, so a copy replaces the destination without the trash
+        assert!(!result.completed[0].replaced);
+        assert_eq!(std::fs::read(dir.join("dest").join("a.mp4")).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(dir.join("dest")).unwrap().count(), 1);
+        assert!(source.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cancelled_overwrite_keeps_the_destination() {
+        let dir = test_dir("cancel");
+        let source = dir.join("a.mp4");
+        std::fs::write(&source, b"new").unwrap();
+        std::fs::write(dir.join("dest").join("a.mp4"), b"old").unwrap();
+
+        // Copies take the same path as a move across volumes
+        let mut job = job(Mode::Copy, ConflictPolicy::Overwrite);
+        job.cancelled.store(true, Ordering::Relaxed);
+        let dest = dir.join("dest").join("a.mp4");
+        assert!(!job.transfer_file(0, &source, &dest, 3, true).unwrap());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old");
+
+        job.cancelled.store(false, Ordering::Relaxed);
+        assert!(job.transfer_file(0, &dir.join("missing.mp4"), &dest, 0, true).is_err());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old");
+
+        // No partial file is left behind
+        assert_eq!(std::fs::read_dir(dir.join("dest")).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let dir = test_dir("policy");
+        let (a, b) = (dir.join("a.mp4"), dir.join("b.mp4"));
+        std::fs::write(&a, b"a").unwrap();
+        std::fs::write(&b, b"b").unwrap();
+        std::fs::write(dir.join("dest").join("a.mp4"), b"old").unwrap();
+        let from = vec![a.to_string_lossy().to_string(), b.to_string_lossy().to_string()];
+
+        let skipped = transfer(job(Mode::Copy, ConflictPolicy::Skip), from.clone(), dir.join("dest")).unwrap();
+        assert_eq!(skipped.skipped, vec![from[0].clone()]);
+        assert_eq!(skipped.completed.len(), 1);
+
+        // Without a channel nobody can answer, so asking skips
+        let asked = transfer(job(Mode::Copy, ConflictPolicy::Ask), from.clone(), dir.join("dest")).unwrap();
+        assert_eq!(asked.skipped.len(), 2);
+
+        let renamed = transfer(job(Mode::Move, ConflictPolicy::Rename), from.clone(), dir.join("dest")).unwrap();
+        assert_eq!(renamed.completed.len(), 2);
+        assert!(renamed.completed[0].to.ends_with("a (1).mp4"));
+        assert!(renamed.completed.iter().all(|file| !file.replaced));
+        assert_eq!(std::fs::read(dir.join("dest").join("a.mp4")).unwrap(), b"old");
+        assert!(!a.exists() && !b.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+
+pub fn trash(file_path: &str) -> Result<(), String> {
+    zouni::fs::trash(file_path)
+}
+
+/// Moves the most recently trashed item that came from `original` back to its location
+pub fn restore(original: &Path) -> Result<(), String> {
+    if original.exists() {
+        return Err(format!("Cannot restore {} because it already exists", original.to_string_lossy()));
+    }
+
+    let entry = find_entry(original)?.ok_or(format!("{} was not found in the trash", original.to_string_lossy()))?;
+    move_back(&entry.file, original)?;
+    let _ = std::fs::remove_file(&entry.info);
+    Ok(())
+}
+
+struct TrashEntry {
+    file: PathBuf,
+    info: PathBuf,
+    deleted_at: String,
+}
+
+fn move_back(from: &Path, to: &Path) -> Result<(), String> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to).map_err(|e| e.to_string())?;
+    std::fs::remove_file(from).map_err(|e| e.to_string())
+}
+
+fn latest(entries: Vec<TrashEntry>) -> Option<TrashEntry> {
+    entries.into_iter().max_by(|a, b| a.deleted_at.cmp(&b.deleted_at))
+}
+
+#[cfg(target_os = "linux")]
+fn find_entry(original: &Path) -> Result<Option<TrashEntry>, String> {
+    let mut entries = Vec::new();
+
+    for (trash_dir, top_dir) in trash_dirs(original) {
+        let Ok(infos) = std::fs::read_dir(trash_dir.join("info")) else {
+            continue;
+        };
+
+        for info in infos.flatten() {
+            let info_path = info.path();
+            if info_path.extension().map(|ext| ext != "trashinfo").unwrap_or(true) {
+                continue;
+            }
+
+            let Some((path, deleted_at)) = std::fs::read_to_string(&info_path).ok().and_then(|content| parse_trash_info(&content)) else {
+                continue;
+            };
+
+            let trashed_from = match &top_dir {
+                Some(top_dir) if !path.starts_with('/') => top_dir.join(&path),
+                _ => PathBuf::from(&path),
+            };
+
+            if trashed_from == original {
+                let name = info_path.file_stem().unwrap_or_default().to_os_string();
+                entries.push(TrashEntry {
+                    file: trash_dir.join("files").join(name),
+                    info: info_path,
+                    deleted_at,
+                });
+            }
+        }
+    }
+
+    Ok(latest(entries))
+}
+
+#[cfg(target_os = "linux")]
+fn trash_dirs(original: &Path) -> Vec<(PathBuf, Option<PathBuf>)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut dirs = Vec::new();
+
+    let data_home = std::env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    if let Some(data_home) = data_home {
+        dirs.push((data_home.join("Trash"), None));
+    }
+
+    // Files on other mounts are trashed into $topdir/.Trash/$uid or $topdir/.Trash-$uid
+    if let Ok(uid) = std::fs::metadata("/proc/self").map(|metadata| metadata.uid()) {
+        for top_dir in original.ancestors().skip(1) {
+            dirs.push((top_dir.join(".Trash").join(uid.to_string()), Some(top_dir.to_path_buf())));
+            dirs.push((top_dir.join(format!(".Trash-{}", uid)), Some(top_dir.to_path_buf())));
+        }
+    }
+
+    dirs.into_iter().filter(|(dir, _)| dir.is_dir()).collect()
+}
+
+/// Reads the decoded original path and the deletion date from a `.trashinfo` file
+#[cfg(any(target_os = "linux", test))]
+fn parse_trash_info(content: &str) -> Option<(String, String)> {
+    let mut path = None;
+    let mut deleted_at = String::new();
+    for line in content.lines() {
+        if let Some(value) = line.strip_prefix("Path=") {
+            path = Some(percent_decode(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deleted_at = value.to_string();
+        }
+    }
+
+    path.map(|path| (path, deleted_at))
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(target_os = "windows")]
+fn find_entry(original: &Path) -> Result<Option<TrashEntry>, String> {
+    use std::path::Component;
+
+    let Some(Component::Prefix(prefix)) = original.components().next() else {
+        return Ok(None);
+    };
+
+    let recycle_bin = PathBuf::from(prefix.as_os_str()).join("\\$Recycle.Bin");
+    let mut entries = Vec::new();
+
+    for sid_dir in std::fs::read_dir(recycle_bin).map_err(|e| e.to_string())?.flatten() {
+        let Ok(items) = std::fs::read_dir(sid_dir.path()) else {
+            continue;
+        };
+
+        for item in items.flatten() {
+            let name = item.file_name().to_string_lossy().to_string();
+            let Some(id) = name.strip_prefix("$I") else {
+                continue;
+            };
+
+            let Ok(data) = std::fs::read(item.path()) else {
+                continue;
+            };
+
+            if let Some((trashed_from, deleted_at)) = parse_recycle_info(&data) {
+                if trashed_from.eq_ignore_ascii_case(&original.to_string_lossy()) {
+                    entries.push(TrashEntry {
+                        file: sid_dir.path().join(format!("$R{}", id)),
+                        info: item.path(),
+                        deleted_at: format!("{:020}", deleted_at),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(latest(entries))
+}
+
+/// Reads the original path and deletion time from a `$I` file. Version 1 stores a fixed 260 character path, version 2 a length-prefixed one.
+#[cfg(any(target_os = "windows", test))]
+fn parse_recycle_info(data: &[u8]) -> Option<(String, u64)> {
+    let read_u64 = |offset: usize| data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    let version = read_u64(0)?;
+    let deleted_at = read_u64(16)?;
+    let name_bytes = match version {
+        1 => data.get(24..24 + 520)?,
+        2 => {
+            let length = u32::from_le_bytes(data.get(24..28)?.try_into().unwrap()) as usize;
+            data.get(28..28 + length * 2)?
+        }
+        _ => return None,
+    };
+
+    let wide: Vec<u16> = name_bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).take_while(|c| *c != 0).collect();
+    Some((String::from_utf16_lossy(&wide), deleted_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recycle_info(version: u64, path: &str, deleted_at: u64) -> Vec<u8> {
+        let wide: Vec<u16> = path.encode_utf16().chain([0]).collect();
+        let mut data = Vec::new();
+        data.extend(version.to_le_bytes());
+        data.extend(1234u64.to_le_bytes());
+        data.extend(deleted_at.to_le_bytes());
+        match version {
+            1 => {
+                let mut name = wide.clone();
+                name.resize(260, 0);
+                data.extend(name.iter().flat_map(|c| c.to_le_bytes()));
+            }
+            _ => {
+                data.extend((wide.len() as u32).to_le_bytes());
+                data.extend(wide.iter().flat_map(|c| c.to_le_bytes()));
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn percent_decode_restores_utf8_and_keeps_invalid_escapes() {
+        assert_eq!(percent_decode("/home/user/My%20Music/%E3%81%82.mp3"), "/home/user/My Music/あ.mp3");
+        assert_eq!(percent_decode("100%25"), "100%");
+        assert_eq!(percent_decode("a%zzb%4"), "a%zzb%4");
+    }
+
+    #[test]
+    fn parse_trash_info_reads_path_and_date() {
+        let content = "[Trash Info]\nPath=/media/a%20b.mp4\nDeletionDate=2024-05-01T10:20:30\n";
+        assert_eq!(parse_trash_info(content), Some((String::from("/media/a b.mp4"), String::from("2024-05-01T10:20:30"))));
+        // Relative to the top directory of the mount
+        assert_eq!(parse_trash_info("[Trash Info]\nPath=videos/x.mp4\n"), Some((String::from("videos/x.mp4"), String::new())));
+        assert_eq!(parse_trash_info("[Trash Info]\nDeletionDate=2024-05-01T10:20:30\n"), None);
+    }
+
+    #[test]
+    fn parse_recycle_info_reads_both_versions() {
+        let path = "C:\\Users\\user\\Videos\\あ.mp4";
+        assert_eq!(parse_recycle_info(&recycle_info(1, path, 42)), Some((String::from(path), 42)));
+        assert_eq!(parse_recycle_info(&recycle_info(2, path, 43)), Some((String::from(path), 43)));
+    }
+
+    #[test]
+    fn parse_recycle_info_rejects_unknown_or_short_data() {
+        let path = "C:\\a.mp4";
+        assert_eq!(parse_recycle_info(&recycle_info(3, path, 1)), None);
+        let data = recycle_info(2, path, 1);
+        assert_eq!(parse_recycle_info(&data[..data.len() - 2]), None);
+        assert_eq!(parse_recycle_info(&data[..20]), None);
+    }
+
+    #[test]
+    fn latest_picks_the_last_deletion() {
+        let entry = |deleted_at: &str| TrashEntry {
+            file: PathBuf::from(deleted_at),
+            info: PathBuf::new(),
+            deleted_at: deleted_at.to_string(),
+        };
+        let found = latest(vec![entry("2024-01-02T00:00:00"), entry("2024-03-01T00:00:00"), entry("2024-02-01T00:00:00")]);
+        assert_eq!(found.map(|entry| entry.file), Some(PathBuf::from("2024-03-01T00:00:00")));
+        assert!(latest(Vec::new()).is_none());
+    }
+}
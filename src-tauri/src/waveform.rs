@@ -0,0 +1,341 @@
+use crate::{
+    cache, convert,
+    probe::{self, Probe},
+    shell,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use tauri::AppHandle;
+
+const WAVEFORM_CACHE: &str = "waveform";
+const WAVEFORM_EXTENSION: &str = "peaks";
+const MAGIC: &[u8; 4] = b"WVPK";
+const VERSION: u8 = 1;
+/// Mono rate the audio is decoded at. Peaks need far less than the playback rate.
+const SAMPLE_RATE: u32 = 8000;
+/// Samples per peak of the finest level, about 31 peaks per second
+const BASE_SAMPLES_PER_PEAK: u32 = 256;
+/// Each level merges this many peaks of the previous one
+const LEVEL_FACTOR: u32 = 8;
+const LEVEL_COUNT: usize = 3;
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveformRequest {
+    pub filePath: String,
+    /// Seconds
+    pub start: f64,
+    /// Seconds, up to the end of the file when omitted
+    pub end: Option<f64>,
+    /// Maximum number of peaks to return, usually the width in pixels
+    pub width: usize,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Serialize)]
+pub struct Waveform {
+    pub start: f64,
+    pub end: f64,
+    pub secondsPerPeak: f64,
+    /// Lowest sample of each peak, from -1 to 1
+    pub min: Vec<f32>,
+    /// Highest sample of each peak, from -1 to 1
+    pub max: Vec<f32>,
+}
+
+struct Level {
+    samples_per_peak: u32,
+    /// Pairs of min and max, scaled to i8
+    peaks: Vec<(i8, i8)>,
+}
+
+struct Peaks {
+    sample_count: u64,
+    levels: Vec<Level>,
+}
+
+/// Peaks of the requested time window, generated from the audio on first use and cached afterwards.
+/// None for files with a video stream, where the picture takes the place of the waveform and decoding the audio would be wasted.
+pub async fn get(app: &AppHandle, request: WaveformRequest) -> Result<Option<Waveform>, String> {
+    if request.width == 0 {
+        return Err(String::from("Width must be greater than zero"));
+    }
+
+    let probe = probe::probe(app, &request.filePath).await?;
+    if convert::select(&probe, "video", None)?.is_some() {
+        return Ok(None);
+    }
+
+    let peaks = match cache::read(app, WAVEFORM_CACHE, &request.filePath, WAVEFORM_EXTENSION).and_then(|data| decode(&data)) {
+        Some(peaks) => peaks,
+        None => {
+            let peaks = generate(app, &request.filePath, &probe).await?;
+            cache::write(app, WAVEFORM_CACHE, &request.filePath, WAVEFORM_EXTENSION, &encode(&peaks))?;
+            peaks
+        }
+    };
+
+    Ok(Some(window(&peaks, &request)))
+}
+
+/// Decodes the audio to raw samples on stdout and reduces them to peaks as they arrive, so the samples are never held or stored
+async fn generate(app: &AppHandle, file_path: &str, probe: &Probe) -> Result<Peaks, String> {
+    let stream = convert::select(probe, "audio", None)?.ok_or_else(|| String::from("The file has no audio stream"))?;
+
+    let args = ["-hide_banner", "-v", "error", "-i", file_path, "-map", &format!("0:{}", stream.index), "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "s16le", "-"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || shell::exec_streaming(&app, "ffmpeg", args, |stdout| compute(std::io::BufReader::new(stdout)))).await.map_err(|e| e.to_string())?
+}
+
+fn compute(mut reader: impl Read) -> Result<Peaks, String> {
+    let mut finest = Vec::new();
+    let mut sample_count: u64 = 0;
+    let mut bucket: Option<(i16, i16)> = None;
+    let mut in_bucket = 0;
+    let mut bytes = [0u8; 2];
+
+    loop {
+        match reader.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.to_string()),
+        }
+
+        let sample = i16::from_le_bytes(bytes);
+        bucket = Some(bucket.map_or((sample, sample), |(min, max)| (min.min(sample), max.max(sample))));
+        sample_count += 1;
+        in_bucket += 1;
+
+        if in_bucket == BASE_SAMPLES_PER_PEAK {
+            finest.extend(bucket.take().map(to_peak));
+            in_bucket = 0;
+        }
+    }
+
+    finest.extend(bucket.map(to_peak));
+
+    if finest.is_empty() {
+        return Err(String::from("The audio stream has no samples"));
+    }
+
+    let mut levels = vec![Level {
+        samples_per_peak: BASE_SAMPLES_PER_PEAK,
+        peaks: finest,
+    }];
+
+    while levels.len() < LEVEL_COUNT {
+        let previous = levels.last().unwrap();
+        levels.push(Level {
+            samples_per_peak: previous.samples_per_peak * LEVEL_FACTOR,
+            peaks: previous.peaks.chunks(LEVEL_FACTOR as usize).map(merge).collect(),
+        });
+    }
+
+    Ok(Peaks {
+        sample_count,
+        levels,
+    })
+}
+
+fn to_peak((min, max): (i16, i16)) -> (i8, i8) {
+    ((min >> 8) as i8, (max >> 8) as i8)
+}
+
+fn merge(peaks: &[(i8, i8)]) -> (i8, i8) {
+    peaks.iter().fold((i8::MAX, i8::MIN), |(min, max), peak| (min.min(peak.0), max.max(peak.1)))
+}
+
+/// Picks the coarsest level that still has a peak per pixel and merges the surplus
+fn window(peaks: &Peaks, request: &WaveformRequest) -> Waveform {
+    let duration = peaks.sample_count as f64 / SAMPLE_RATE as f64;
+    let start = request.start.clamp(0.0, duration);
+    let end = request.end.unwrap_or(duration).clamp(start, duration);
+
+    let range = |level: &Level| {
+        let seconds_per_peak = level.samples_per_peak as f64 / SAMPLE_RATE as f64;
+        let first = ((start / seconds_per_peak).floor() as usize).min(level.peaks.len());
+        let last = ((end / seconds_per_peak).ceil() as usize).clamp(first, level.peaks.len());
+        (first, last)
+    };
+
+    let level = peaks
+        .levels
+        .iter()
+        .rev()
+        .find(|level| {
+            let (first, last) = range(level);
+            last - first >= request.width
+        })
+        .unwrap_or(&peaks.levels[0]);
+
+    let (first, last) = range(level);
+    let selected = &level.peaks[first..last];
+    let count = selected.len().min(request.width);
+
+    let merged: Vec<(i8, i8)> = (0..count).map(|i| merge(&selected[i * selected.len() / count..(i + 1) * selected.len() / count])).collect();
+
+    Waveform {
+        start,
+        end,
+        secondsPerPeak: if count > 0 {
+            (end - start) / count as f64
+        } else {
+            0.0
+        },
+        min: merged.iter().map(|(min, _)| *min as f32 / 128.0).collect(),
+        max: merged.iter().map(|(_, max)| *max as f32 / 128.0).collect(),
+    }
+}
+
+/// "WVPK", version, sample rate, sample count and level count, then per level its samples per peak, peak count and min/max pairs
+fn encode(peaks: &Peaks) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(MAGIC);
+    data.push(VERSION);
+    data.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    data.extend_from_slice(&peaks.sample_count.to_le_bytes());
+    data.push(peaks.levels.len() as u8);
+
+    for level in &peaks.levels {
+        data.extend_from_slice(&level.samples_per_peak.to_le_bytes());
+        data.extend_from_slice(&(level.peaks.len() as u32).to_le_bytes());
+        for (min, max) in &level.peaks {
+            data.extend_from_slice(&[*min as u8, *max as u8]);
+        }
+    }
+
+    data
+}
+
+/// None for data written by another version, which is then generated again
+fn decode(data: &[u8]) -> Option<Peaks> {
+    let mut data = data;
+    let mut take = |length: usize| {
+        let (head, rest) = data.split_at_checked(length)?;
+        data = rest;
+        Some(head)
+    };
+
+    if take(4)? != MAGIC || take(1)?[0] != VERSION {
+        return None;
+    }
+
+    if u32::from_le_bytes(take(4)?.try_into().ok()?) != SAMPLE_RATE {
+        return None;
+    }
+
+    let sample_count = u64::from_le_bytes(take(8)?.try_into().ok()?);
+    let level_count = take(1)?[0] as usize;
+
+    let mut levels = Vec::with_capacity(level_count);
+    for _ in 0..level_count {
+        let samples_per_peak = u32::from_le_bytes(take(4)?.try_into().ok()?);
+        let count = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let peaks = take(count * 2)?.chunks_exact(2).map(|pair| (pair[0] as i8, pair[1] as i8)).collect();
+        levels.push(Level {
+            samples_per_peak,
+            peaks,
+        });
+    }
+
+    if levels.is_empty() {
+        return None;
+    }
+
+    Some(Peaks {
+        sample_count,
+        levels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+    }
+
+    fn request(start: f64, end: Option<f64>, width: usize) -> WaveformRequest {
+        WaveformRequest {
+            filePath: String::new(),
+            start,
+            end,
+            width,
+        }
+    }
+
+    #[test]
+    fn computes_peaks_for_every_level() {
+        // Two full peaks and a partial one
+        let mut samples = vec![0i16; BASE_SAMPLES_PER_PEAK as usize * 2 + 10];
+        samples[3] = i16::MAX;
+        samples[BASE_SAMPLES_PER_PEAK as usize + 5] = i16::MIN;
+        samples[BASE_SAMPLES_PER_PEAK as usize * 2 + 1] = 256;
+
+        let peaks = compute(pcm(&samples).as_slice()).unwrap();
+
+        assert_eq!(peaks.sample_count, samples.len() as u64);
+        assert_eq!(peaks.levels.len(), LEVEL_COUNT);
+        assert_eq!(peaks.levels[0].peaks, [(0, 127), (-128, 0), (0, 1)]);
+        assert_eq!(peaks.levels[1].samples_per_peak, BASE_SAMPLES_PER_PEAK * LEVEL_FACTOR);
+        assert_eq!(peaks.levels[1].peaks, [(-128, 127)]);
+    }
+
+    #[test]
+    fn rejects_empty_audio() {
+        assert!(compute([1u8].as_slice()).is_err());
+    }
+
+    #[test]
+    fn encoded_peaks_decode_to_the_same() {
+        let samples: Vec<i16> = (0..20_000).map(|i| ((i * 37) % 65_536 - 32_768) as i16).collect();
+        let peaks = compute(pcm(&samples).as_slice()).unwrap();
+
+        let data = encode(&peaks);
+        let decoded = decode(&data).unwrap();
+
+        assert_eq!(decoded.sample_count, peaks.sample_count);
+        assert_eq!(decoded.levels.len(), peaks.levels.len());
+        for (decoded, level) in decoded.levels.iter().zip(&peaks.levels) {
+            assert_eq!(decoded.samples_per_peak, level.samples_per_peak);
+            assert_eq!(decoded.peaks, level.peaks);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions_and_truncated_data() {
+        let peaks = compute(pcm(&[1, 2, 3]).as_slice()).unwrap();
+        let data = encode(&peaks);
+
+        assert!(decode(&data[..data.len() - 1]).is_none());
+
+        let mut other_version = data.clone();
+        other_version[4] = VERSION + 1;
+        assert!(decode(&other_version).is_none());
+
+        assert!(decode(b"WAVE").is_none());
+    }
+
+    #[test]
+    fn windows_pick_a_level_and_fit_the_width() {
+        let samples = vec![0i16; BASE_SAMPLES_PER_PEAK as usize * LEVEL_FACTOR as usize * 100];
+        let peaks = compute(pcm(&samples).as_slice()).unwrap();
+        let duration = samples.len() as f64 / SAMPLE_RATE as f64;
+
+        let whole = window(&peaks, &request(0.0, None, 50));
+        assert_eq!(whole.max.len(), 50);
+        assert_eq!((whole.start, whole.end), (0.0, duration));
+
+        let part = window(&peaks, &request(1.0, Some(2.0), 10_000));
+        assert!(part.max.len() <= 32);
+        assert_eq!((part.start, part.end), (1.0, 2.0));
+
+        let clamped = window(&peaks, &request(duration + 5.0, None, 10));
+        assert_eq!((clamped.start, clamped.end, clamped.max.len()), (duration, duration, 0));
+    }
+}
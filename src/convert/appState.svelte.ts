@@ -1,3 +1,5 @@
+import type { ProbeStream } from "../ipc";
+
 type AppState = {
     audioVolume: string;
     maxVolume: boolean;
@@ -10,6 +12,15 @@ type AppState = {
     rotation: Mp.VideoRotation;
     sourceFile: string;
     sourceType: Mp.ConvertType;
+    streams: ProbeStream[];
+    videoStream: string;
+    audioStream: string;
+    subtitleStream: string;
+    burnSubtitle: boolean;
+    removeSilence: boolean;
+    extractFormat: Mp.ExtractFormat;
+    extractSplit: Mp.ExtractSplit;
+    extractToken: string;
 };
 
 export const appState: AppState = $state({
@@ -24,4 +35,13 @@ export const appState: AppState = $state({
     rotation: "RotationNone",
     sourceFile: "",
     sourceType: "Video",
+    streams: [],
+    videoStream: "default",
+    audioStream: "default",
+    subtitleStream: "none",
+    burnSubtitle: false,
+    removeSilence: false,
+    extractFormat: "Copy",
+    extractSplit: "None",
+    extractToken: "",
 });
@@ -1,5 +1,5 @@
 import { listen, emit, UnlistenFn, once, emitTo, EventName } from "@tauri-apps/api/event";
-import { invoke } from "@tauri-apps/api/core";
+import { invoke, Channel } from "@tauri-apps/api/core";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 
 type TauriCommand<Req, Res> = {
@@ -12,9 +12,37 @@ type RenameInfo = {
     old: string;
 };
 
-type MoveInfo = {
+type ConflictPolicy = "Skip" | "Overwrite" | "Rename" | "Ask";
+
+export type ConflictAnswer = "Skip" | "Overwrite" | "Rename" | "Cancel";
+
+export type TransferEvent =
+    | { Started: { total_files: number; total_bytes: number } }
+    | { FileStarted: { index: number; from: string; to: string; size: number } }
+    | { Progress: { index: number; file_bytes: number; file_size: number; transferred_bytes: number; total_bytes: number } }
+    | { Conflict: { index: number; from: string; to: string } }
+    | { FileFinished: { index: number; from: string; to: string; skipped: boolean } }
+    | { Finished: { cancelled: boolean } };
+
+type TransferInfo = {
     from: string[];
     to: string;
+    policy?: ConflictPolicy;
+    cancellation_token?: string;
+    on_progress?: Channel<TransferEvent>;
+};
+
+type ConflictAnswerInfo = {
+    cancellation_token: string;
+    answer: ConflictAnswer;
+    apply_to_all?: boolean;
+};
+
+export type TransferResult = {
+    completed: MovedFile[];
+    skipped: string[];
+    failed: { file: string; error: string }[];
+    cancelled: boolean;
 };
 
 type FileAttribute = {
@@ -108,13 +136,304 @@ type MessageResult = {
     cancelled: boolean;
 };
 
+type TagRenameOptions = {
+    files: string[];
+    template: string;
+};
+
+type TagRenameStatus = "Ready" | "Unchanged" | "MissingTags" | "Exists" | "Duplicate" | "ProbeFailed";
+
+export type TagRenameEntry = {
+    old: string;
+    new: string;
+    status: TagRenameStatus;
+    missing: string[];
+};
+
+export type TagRenameReport = {
+    entries: TagRenameEntry[];
+    collisions: number;
+};
+
+type TagRenameResult = {
+    renamed: TagRenameEntry[];
+    failed: { old: string; error: string }[];
+};
+
+type MovedFile = {
+    from: string;
+    to: string;
+    replaced: boolean;
+};
+
+export type FileOperation = { Trash: string[] } | { Move: MovedFile[] } | { Rename: MovedFile };
+
+type JournalResult = {
+    done: FileOperation | null;
+    error: string | null;
+};
+
+type MediaSession = {
+    status: Mp.PlaybackStatus;
+    file_path: string;
+    title: string;
+    artist: string[];
+    album: string;
+    art_url: string | null;
+    duration: number;
+    position: number;
+    volume: number;
+    rate: number;
+    seeked?: boolean;
+};
+
+type ShortcutStatus = {
+    registered: string[];
+    conflicts: { accelerator: string; actions: string[] }[];
+    failed: { action: string; accelerator: string; error: string }[];
+};
+
+type MenuItemUpdate = {
+    id: string;
+    disabled?: boolean;
+    checked?: boolean;
+    group?: string;
+};
+
+type MenuUpdate = {
+    menu: "Player" | "Playlist" | "Sort";
+    items: MenuItemUpdate[];
+};
+
+type CustomSpeed = {
+    kind: "PlaybackSpeed" | "SeekSpeed";
+    value: number;
+};
+
+type SpeedPresets = {
+    kind: "PlaybackSpeed" | "SeekSpeed";
+    presets: number[];
+};
+
+export type Chapter = {
+    id: number;
+    start: number;
+    end: number;
+    title: string;
+};
+
+export type Waveform = {
+    start: number;
+    end: number;
+    secondsPerPeak: number;
+    min: number[];
+    max: number[];
+};
+
+type WaveformRequest = {
+    filePath: string;
+    start: number;
+    end?: number;
+    width: number;
+};
+
+export type SceneMarker = {
+    time: number;
+    score: number;
+    thumbnail: string | null;
+};
+
+type SceneDetection = {
+    filePath: string;
+    threshold: number;
+    minInterval?: number;
+    cancellationToken: string;
+};
+
+type SceneSave = {
+    filePath: string;
+    markers: SceneMarker[];
+    target: "Bookmarks" | { Chapters: string };
+};
+
+type SilenceRequest = {
+    filePath: string;
+    cancellationToken: string;
+};
+
+export type SilenceInterval = {
+    start: number;
+    end: number;
+};
+
+export type PlaylistEntry = {
+    id: string;
+    fullPath: string;
+};
+
+export type PlaylistSnapshot = {
+    entries: PlaylistEntry[];
+    currentIndex: number | null;
+    currentFile: PlaylistEntry | null;
+    shuffle: boolean;
+    seed: number;
+    repeat: Mp.RepeatMode;
+};
+
+type PlaylistReplace = {
+    entries: PlaylistEntry[];
+    currentIndex: number | null;
+};
+
+type ShuffleRequest = {
+    shuffle: boolean;
+    seed?: number;
+};
+
+export type AbLoop = {
+    start: number | null;
+    end: number | null;
+};
+
+type AbLoopUpdate = AbLoop & {
+    filePath: string;
+};
+
+type AbLoopExport = {
+    filePath: string;
+    file: string;
+};
+
+type ReplayGainRequest = {
+    filePath: string;
+    album: boolean;
+};
+
+type ReplayGain = {
+    gain: number;
+    album: boolean;
+};
+
+type SubtitleTrack = {
+    id: string;
+    source: { Sidecar: { path: string } } | { Embedded: { index: number } };
+    format: string;
+    language: string | null;
+    title: string | null;
+    default: boolean;
+};
+
+type SubtitleList = {
+    tracks: SubtitleTrack[];
+    selected: string | null;
+    offset: number;
+};
+
+type SubtitleRequest = {
+    filePath: string;
+    trackId: string | null;
+};
+
+type SyncPoint = {
+    from: number;
+    to: number;
+};
+
+type SubtitleExport = {
+    filePath: string;
+    trackId: string;
+    timing?: { Offset: number } | { Stretch: [SyncPoint, SyncPoint] };
+    file: string;
+};
+
+export type ProbeStream = {
+    index: number;
+    codec_type: string;
+    codec_name: string;
+    tags: { [key: string]: string };
+};
+
+type ConvertStreamsRequest = {
+    filePath: string;
+    convertType: Mp.ConvertType;
+    format: string;
+    selection: Mp.StreamSelection;
+    removeSilence: boolean;
+};
+
+type ConvertStreams = {
+    video: number | null;
+    audio: number | null;
+    subtitle: number | null;
+    args: string[];
+    videoFilter: string | null;
+    audioFilter: string | null;
+};
+
+type AudioExtraction = {
+    filePath: string;
+    outputDir: string;
+    audio?: Mp.StreamSelector;
+    format: Mp.ExtractFormat;
+    bitrate?: number;
+    quality?: number;
+    split?: "Chapters" | { Cue: string };
+    cancellationToken: string;
+};
+
+type TagEdit = {
+    filePath: string;
+    tags: { [key: string]: string };
+    coverArt: string | null;
+    backup: boolean;
+};
+
+export type Bookmark = {
+    id: string;
+    time: number;
+    label: string;
+    color: string;
+    note: string | null;
+};
+
+type NewBookmark = {
+    filePath: string;
+    time: number;
+    label: string;
+    color?: string;
+    note?: string;
+};
+
+type BookmarkTransfer = {
+    filePath: string;
+    file: string;
+    format: "Json" | "Chapters";
+};
+
 type TauriCommandMap = {
-    prepare_windows: TauriCommand<Mp.TauriSettings, boolean>;
+    prepare_windows: TauriCommand<undefined, boolean>;
     get_init_args: TauriCommand<undefined, string[]>;
     open_context_menu: TauriCommand<Mp.Position, undefined>;
     open_list_context_menu: TauriCommand<Mp.Position, undefined>;
     open_sort_context_menu: TauriCommand<Mp.Position, undefined>;
     change_theme: TauriCommand<Mp.Theme, undefined>;
+    change_locale: TauriCommand<Mp.Settings["locale"], string>;
+    validate_speed: TauriCommand<CustomSpeed, number>;
+    set_speed_presets: TauriCommand<SpeedPresets, Mp.Settings>;
+    load_chapters: TauriCommand<string, Chapter[]>;
+    list_subtitles: TauriCommand<string, SubtitleList>;
+    load_subtitle: TauriCommand<SubtitleRequest, string>;
+    select_subtitle: TauriCommand<SubtitleRequest, undefined>;
+    set_subtitle_offset: TauriCommand<{ filePath: string; offset: number }, number>;
+    export_subtitle: TauriCommand<SubtitleExport, undefined>;
+    load_bookmarks: TauriCommand<string, Bookmark[]>;
+    get_bookmarks: TauriCommand<string, Bookmark[]>;
+    add_bookmark: TauriCommand<NewBookmark, Bookmark>;
+    update_bookmark: TauriCommand<{ filePath: string; bookmark: Bookmark }, Bookmark>;
+    remove_bookmark: TauriCommand<{ filePath: string; id: string }, undefined>;
+    export_bookmarks: TauriCommand<BookmarkTransfer, undefined>;
+    import_bookmarks: TauriCommand<BookmarkTransfer, Bookmark[]>;
+    update_menu: TauriCommand<MenuUpdate, undefined>;
     set_sort: TauriCommand<Mp.SortType, undefined>;
     get_sort: TauriCommand<undefined, Mp.SortType | undefined>;
     reveal: TauriCommand<string, undefined>;
@@ -122,8 +441,13 @@ type TauriCommandMap = {
     remove: TauriCommand<string, undefined>;
     exists: TauriCommand<string, boolean>;
     rename: TauriCommand<RenameInfo, boolean>;
+    undo_last_operation: TauriCommand<undefined, JournalResult>;
+    redo_operation: TauriCommand<undefined, JournalResult>;
     stat: TauriCommand<string, FileAttribute>;
-    mv_all: TauriCommand<MoveInfo, undefined>;
+    mv_all: TauriCommand<TransferInfo, TransferResult>;
+    copy_all: TauriCommand<TransferInfo, TransferResult>;
+    cancel_transfer: TauriCommand<string, undefined>;
+    answer_transfer_conflict: TauriCommand<ConflictAnswerInfo, undefined>;
     is_uris_available: TauriCommand<undefined, boolean>;
     read_uris: TauriCommand<undefined, ClipboardData>;
     read_text: TauriCommand<undefined, string>;
@@ -134,10 +458,16 @@ type TauriCommandMap = {
     create: TauriCommand<string, undefined>;
     read_text_file: TauriCommand<string, string>;
     write_text_file: TauriCommand<WriteFileInfo, undefined>;
+    get_settings: TauriCommand<undefined, Mp.Settings>;
+    update_settings: TauriCommand<Partial<Mp.Settings>, Mp.Settings>;
+    get_settings_path: TauriCommand<undefined, string>;
     write_all: TauriCommand<WriteAllFileInfo, undefined>;
     stat_all: TauriCommand<string[], FileAttributeExt[]>;
     set_play_thumbs: TauriCommand<any, undefined>;
     set_pause_thumbs: TauriCommand<any, undefined>;
+    update_media_session: TauriCommand<MediaSession, undefined>;
+    get_global_shortcut_status: TauriCommand<undefined, ShortcutStatus>;
+    get_keymap: TauriCommand<undefined, Mp.Keymap>;
     spawn: TauriCommand<SpawnOption, CommandResult>;
     kill: TauriCommand<string, undefined>;
     message: TauriCommand<DialogOptions, MessageResult>;
@@ -147,21 +477,35 @@ type TauriCommandMap = {
     listen_file_drop: TauriCommand<string, undefined>;
     unlisten_file_drop: TauriCommand<undefined, undefined>;
     get_media_metadata: TauriCommand<string, any>;
+    resolve_convert_streams: TauriCommand<ConvertStreamsRequest, ConvertStreams>;
+    extract_audio: TauriCommand<AudioExtraction, string[]>;
+    get_tags: TauriCommand<string, { [key: string]: string }>;
+    write_tags: TauriCommand<TagEdit, any>;
+    get_cover_art: TauriCommand<string, string | null>;
+    get_waveform: TauriCommand<WaveformRequest, Waveform | null>;
+    get_silence: TauriCommand<SilenceRequest, SilenceInterval[]>;
+    change_silence_mode: TauriCommand<Mp.SilenceMode, undefined>;
+    get_replay_gain: TauriCommand<ReplayGainRequest, ReplayGain>;
+    change_levelling_mode: TauriCommand<Mp.LevellingMode, undefined>;
+    get_ab_loop: TauriCommand<string, AbLoop>;
+    set_ab_loop: TauriCommand<AbLoopUpdate, AbLoop>;
+    export_ab_loop: TauriCommand<AbLoopExport, undefined>;
+    get_playlist: TauriCommand<undefined, PlaylistSnapshot>;
+    playlist_replace: TauriCommand<PlaylistReplace, PlaylistSnapshot>;
+    playlist_next: TauriCommand<boolean, PlaylistSnapshot | null>;
+    playlist_previous: TauriCommand<undefined, PlaylistSnapshot | null>;
+    playlist_jump: TauriCommand<number, PlaylistSnapshot>;
+    playlist_insert_next: TauriCommand<PlaylistEntry[], PlaylistSnapshot>;
+    playlist_set_shuffle: TauriCommand<ShuffleRequest, PlaylistSnapshot>;
+    playlist_set_repeat: TauriCommand<Mp.RepeatMode, PlaylistSnapshot>;
+    detect_scenes: TauriCommand<SceneDetection, SceneMarker[]>;
+    save_scene_markers: TauriCommand<SceneSave, undefined>;
+    preview_tag_rename: TauriCommand<TagRenameOptions, TagRenameReport>;
+    apply_tag_rename: TauriCommand<TagRenameEntry[], TagRenameResult>;
     undo: TauriCommand<undefined, undefined>;
     redo: TauriCommand<undefined, undefined>;
 };
 
-export const toTauriSettings = (settings: Mp.Settings): Mp.TauriSettings => {
-    return {
-        theme: settings.theme,
-        fitToWindow: settings.video.fitToWindow,
-        playbackSpeed: settings.video.playbackSpeed,
-        seekSpeed: settings.video.seekSpeed,
-        groupBy: settings.sort.groupBy,
-        order: settings.sort.order,
-    };
-};
-
 export class IPCBase {
     getWindow = async (label: RendererName) => {
         return WebviewWindow.getByLabel(label);
@@ -86,7 +86,7 @@ type AppAction =
     | { type: "startRename"; value: { rect: RenamePartialRect; value: string } }
     | { type: "endRename" }
     | { type: "preventBlur"; value: boolean }
-    | { type: "toggleShuffle" }
+    | { type: "shuffle"; value: boolean }
     | { type: "rename"; value: Mp.MediaFile }
     | { type: "udpateName"; value: string }
     | { type: "startDrag"; value: { startId: string; dir: string } }
@@ -173,8 +173,8 @@ const updater = (state: AppState, action: AppAction) => {
         case "preventBlur":
             return { ...state, preventBlur: action.value };
 
-        case "toggleShuffle":
-            return { ...state, shuffle: !state.shuffle };
+        case "shuffle":
+            return { ...state, shuffle: action.value };
 
         case "toggleSearch":
             return { ...state, searchState: { ...state.searchState, searching: action.value } };
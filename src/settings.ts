@@ -1,12 +1,10 @@
-import { appDataDir } from "@tauri-apps/api/path";
-import path from "./path";
-import util from "./util";
+import { listen } from "@tauri-apps/api/event";
 import { IPCBase } from "./ipc";
 
 const ipc = new IPCBase();
-const JSON_NAME = "taltmediaplayer.settings.json";
 
 const defaultSettings: Mp.Settings = {
+    version: 1,
     bounds: { width: 1200, height: 800, x: 0, y: 0 },
     playlistBounds: { width: 400, height: 700, x: 0, y: 0 },
     isMaximized: false,
@@ -16,26 +14,49 @@ const defaultSettings: Mp.Settings = {
         order: "NameAsc",
         groupBy: false,
     },
+    playlist: {
+        repeat: "All",
+        shuffle: false,
+    },
     video: {
         playbackSpeed: 1,
         seekSpeed: 10,
         fitToWindow: true,
+        playbackSpeedPresets: [0.25, 0.5, 0.75, 1, 1.25, 1.5, 1.75, 2],
+        seekSpeedPresets: [0.03, 0.05, 0.1, 0.5, 1, 3, 5, 10, 20],
     },
     audio: {
         volume: 1,
         ampLevel: 0.07,
         mute: false,
+        levelling: "Off",
+    },
+    silence: {
+        mode: "Off",
+        speed: 4,
+        noise: -35,
+        minDuration: 2,
     },
     defaultPath: "",
     locale: {
         mode: "system",
         lang: "en",
     },
+    globalShortcuts: {
+        PlayPause: "MediaPlayPause",
+        Next: "MediaTrackNext",
+        Previous: "MediaTrackPrevious",
+        SeekForward: "",
+        SeekBackward: "",
+        VolumeUp: "",
+        VolumeDown: "",
+        TogglePlaylist: "Ctrl+Alt+P",
+    },
+    keymap: {},
 };
 
 export class Settings {
     data: Mp.Settings;
-    private dataDir = "";
     private file = "";
 
     constructor() {
@@ -43,56 +64,27 @@ export class Settings {
     }
 
     async init(): Promise<Mp.Settings> {
-        this.dataDir = await appDataDir();
-        const settingPath = path.join(this.dataDir, "temp");
-        this.file = path.join(settingPath, JSON_NAME);
-        const fileExists = await util.exists(this.file);
+        this.file = await ipc.invoke("get_settings_path", undefined);
+        this.data = await ipc.invoke("get_settings", undefined);
 
-        if (fileExists) {
-            const rawData = await ipc.invoke("read_text_file", this.file);
-            if (rawData) {
-                this.data = this.createSettings(JSON.parse(rawData));
-            }
-        } else {
-            await ipc.invoke("mkdir_all", settingPath);
-            await ipc.invoke("create", this.file);
-            await ipc.invoke("write_text_file", { fullPath: this.file, data: JSON.stringify(this.data) });
-        }
+        await listen<Mp.Settings>("settings-changed", (e) => {
+            this.data = e.payload;
+        });
 
         return this.data;
     }
 
     async save() {
         try {
-            await ipc.invoke("write_text_file", { fullPath: this.file, data: JSON.stringify(this.data) });
+            this.data = await ipc.invoke("update_settings", this.data);
         } catch (ex: any) {
-            console.log("Failed to save settings");
+            // The change was rejected, so keep what is saved
+            this.data = await ipc.invoke("get_settings", undefined);
+            await ipc.invoke("message", { dialog_type: "message", message: `Failed to save settings: ${ex}`, kind: "error" });
         }
     }
 
     getSettingsFilePath() {
         return this.file;
     }
-
-    private createSettings(rawSettings: any): Mp.Settings {
-        const config = { ...defaultSettings } as any;
-
-        Object.keys(rawSettings).forEach((key) => {
-            if (!(key in config)) return;
-
-            const value = rawSettings[key];
-
-            if (typeof value === "object" && !Array.isArray(value)) {
-                Object.keys(value).forEach((valueKey) => {
-                    if (valueKey in config[key]) {
-                        config[key][valueKey] = value[valueKey];
-                    }
-                });
-            } else {
-                config[key] = value;
-            }
-        });
-
-        return config;
-    }
 }
@@ -1,78 +1,51 @@
-export const handleShortcut = (renderer: RendererName, e: KeyboardEvent): Mp.ContextMenuEvent | null => {
-    if (renderer === "Player") {
-        return handlePlayerShortcut(e);
-    }
+import { listen } from "@tauri-apps/api/event";
+import { IPCBase } from "./ipc";
 
-    if (renderer === "Playlist") {
-        return handlePlaylistShortcut(e);
-    }
+const ipc = new IPCBase();
 
-    return null;
-};
+const MODIFIERS = ["Ctrl", "Alt", "Shift", "Meta"] as const;
+const KEEP_DEFAULT: Mp.ContextMenuEvent["id"][] = ["PasteFilePath"];
 
-const handlePlayerShortcut = (e: KeyboardEvent): Mp.ContextMenuEvent | null => {
-    if (e.key === "F11") {
-        e.preventDefault();
-        return { id: "ToggleFullscreen", name: "" };
-    }
+let keymap: Mp.Keymap = {};
 
-    if (e.ctrlKey && e.key === "s") {
-        e.preventDefault();
-        return { id: "Capture", name: "" };
-    }
+export const initKeymap = async () => {
+    keymap = await ipc.invoke("get_keymap", undefined);
 
-    if (e.ctrlKey && e.shiftKey && e.key === "P") {
-        e.preventDefault();
-        return { id: "ViewSettingsJson", name: "" };
-    }
+    await listen<Mp.Keymap>("keymap-changed", (e) => {
+        keymap = e.payload;
+    });
+};
 
-    if (e.ctrlKey && e.key === "p") {
-        e.preventDefault();
-        return { id: "TogglePlaylistWindow", name: "" };
-    }
+const toKey = (e: KeyboardEvent) => {
+    // Letters and digits come from the physical key, since Shift changes e.key ("!" for Shift+1)
+    if (/^Key[A-Z]$/.test(e.code)) return e.code.slice(3);
+    if (/^Digit[0-9]$/.test(e.code)) return e.code.slice(5);
+    if (e.key === " ") return "Space";
+    return e.key.length === 1 ? e.key.toUpperCase() : e.key;
+};
 
-    return null;
+const toAccelerator = (e: KeyboardEvent) => {
+    const pressed = [e.ctrlKey, e.altKey, e.shiftKey, e.metaKey];
+    const modifiers = MODIFIERS.filter((_, i) => pressed[i]);
+    return [...modifiers, toKey(e)].join("+");
 };
 
-const handlePlaylistShortcut = (e: KeyboardEvent): Mp.ContextMenuEvent | null => {
-    if (e.ctrlKey && e.key === "p") {
+export const handleShortcut = (renderer: RendererName, e: KeyboardEvent): Mp.ContextMenuEvent | null => {
+    if (renderer === "Playlist" && e.ctrlKey && e.key === "p") {
         e.preventDefault();
         return null;
     }
 
-    if (e.key === "Delete") {
-        e.preventDefault();
-        return { id: "Remove", name: "" };
-    }
-
-    if (e.shiftKey && e.key === "Delete") {
-        e.preventDefault();
-        return { id: "Trash", name: "" };
-    }
-
-    if (e.ctrlKey && e.shiftKey && e.key === "C") {
-        e.preventDefault();
-        return { id: "CopyFullpath", name: "" };
-    }
+    const bindings = keymap[renderer];
+    if (!bindings) return null;
 
-    if (e.ctrlKey && e.key === "c") {
-        e.preventDefault();
-        return { id: "CopyFileName", name: "" };
-    }
-
-    if (e.ctrlKey && e.key === "v") {
-        return { id: "PasteFilePath", name: "" };
-    }
-
-    if (e.ctrlKey && e.key === "r") {
-        e.preventDefault();
-        return { id: "Reveal", name: "" };
-    }
+    const accelerator = toAccelerator(e);
+    const id = Object.keys(bindings).find((id) => bindings[id] === accelerator) as Mp.ContextMenuEvent["id"] | undefined;
+    if (!id) return null;
 
-    if (e.key == "F2") {
+    if (!KEEP_DEFAULT.includes(id)) {
         e.preventDefault();
-        return { id: "Rename", name: "" };
     }
 
-    return null;
+    return { id, name: "" };
 };
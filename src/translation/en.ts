@@ -4,6 +4,7 @@ export const en: Mp.Labels = {
     sort: "Sort",
     playbackSpeed: "Playback Speed",
     seekSpeed: "Seek Speed",
+    customSpeed: "Enter a custom speed",
     fitToWindow: "Fit To Window Size",
     playlist: "Playlist",
     fullscreen: "Toggle Fullscreen",
@@ -40,9 +41,60 @@ export const en: Mp.Labels = {
     frameSize: "Size",
     videoRotation: "Rotation",
     audioBitrate: "Audio Bitrate",
+    videoStream: "Video Track",
+    audioStream: "Audio Track",
+    subtitleStream: "Subtitle Track",
+    burnSubtitle: "burn in",
+    extractAudio: "Extract Audio",
+    split: "Split",
+    splitNone: "None",
+    splitChapters: "By Chapters",
+    splitCue: "By CUE Sheet",
+    selectCueFile: "Select a CUE sheet",
+    selectOutputDir: "Select an output folder",
+    keepBackup: "Keep a backup",
+    save: "Save",
+    sceneThreshold: "Threshold",
+    detect: "Detect",
+    noScenes: "No scene changes found",
+    setLoopPointsFirst: "Set both loop points first",
+    saveAsBookmarks: "Save as Bookmarks",
+    writeChapters: "Write Chapters to a Copy…",
+    removeSilence: "remove silence",
+    streamDefault: "Default",
+    streamNone: "None",
     maximizeVolue: "maximize",
     volume: "Volume",
     start: "Start",
+    renameTemplate: "Template",
+    preview: "Preview",
+    collisions: "Collisions",
+    tagRenameReady: "Ready",
+    tagRenameUnchanged: "Unchanged",
+    tagRenameMissingTags: "Missing tags",
+    tagRenameExists: "Exists",
+    tagRenameDuplicate: "Duplicate",
+    tagRenameProbeFailed: "Unreadable",
+    transferring: "Transferring files",
+    fileExists: "A file with the same name already exists",
+    skip: "Skip",
+    overwrite: "Overwrite",
+    keepBoth: "Keep Both",
+    applyToAll: "Apply to all",
+    speedPresets: "Presets",
+    addPreset: "Add to Presets",
+    apply: "Apply",
+    bookmarks: "Bookmarks",
+    bookmarkLabel: "Label",
+    bookmarkColor: "Color",
+    bookmarkNote: "Note",
+    noBookmarks: "No bookmarks",
+    syncSubtitles: "Sync Subtitles",
+    syncPoint: "Sync Point",
+    subtitleCue: "Subtitle line",
+    useCurrentTime: "Use Current Time",
+    saveAs: "Save As…",
+    globalShortcutsUnavailable: "Some global shortcuts could not be set. Change globalShortcuts in the settings file:",
     cancel: "Cancel",
     close: "Close",
     mute: "Mute",
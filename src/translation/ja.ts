@@ -4,6 +4,7 @@ export const ja: Mp.Labels = {
     sort: "ソート",
     playbackSpeed: "再生速度",
     seekSpeed: "シーク速度",
+    customSpeed: "速度を入力してください",
     fitToWindow: "ウィンドウサイズに合わせる",
     playlist: "プレイリスト",
     fullscreen: "フルスクリーン",
@@ -40,9 +41,60 @@ export const ja: Mp.Labels = {
     frameSize: "サイズ",
     videoRotation: "回転",
     audioBitrate: "ビットレート（オーディオ）",
+    videoStream: "映像トラック",
+    audioStream: "音声トラック",
+    subtitleStream: "字幕トラック",
+    burnSubtitle: "焼き込み",
+    extractAudio: "音声を抽出",
+    split: "分割",
+    splitNone: "なし",
+    splitChapters: "チャプターごと",
+    splitCue: "CUEシートごと",
+    selectCueFile: "CUEシートを選択",
+    selectOutputDir: "出力先フォルダを選択",
+    keepBackup: "バックアップを残す",
+    save: "保存",
+    sceneThreshold: "しきい値",
+    detect: "検出",
+    noScenes: "シーンの切り替わりは見つかりませんでした",
+    setLoopPointsFirst: "A点とB点の両方を設定してください",
+    saveAsBookmarks: "ブックマークとして保存",
+    writeChapters: "チャプターをコピーに書き込む…",
+    removeSilence: "無音部分を削除",
+    streamDefault: "デフォルト",
+    streamNone: "なし",
     maximizeVolue: "最大化",
     volume: "音量",
     start: "開始",
+    renameTemplate: "テンプレート",
+    preview: "プレビュー",
+    collisions: "衝突",
+    tagRenameReady: "変更可",
+    tagRenameUnchanged: "変更なし",
+    tagRenameMissingTags: "タグがありません",
+    tagRenameExists: "既に存在します",
+    tagRenameDuplicate: "重複",
+    tagRenameProbeFailed: "読み込めません",
+    transferring: "ファイルを転送中",
+    fileExists: "同じ名前のファイルが既に存在します",
+    skip: "スキップ",
+    overwrite: "上書き",
+    keepBoth: "両方残す",
+    applyToAll: "すべてに適用",
+    speedPresets: "プリセット",
+    addPreset: "プリセットに追加",
+    apply: "適用",
+    bookmarks: "ブックマーク",
+    bookmarkLabel: "ラベル",
+    bookmarkColor: "色",
+    bookmarkNote: "メモ",
+    noBookmarks: "ブックマークはありません",
+    syncSubtitles: "字幕を同期",
+    syncPoint: "同期ポイント",
+    subtitleCue: "字幕の行",
+    useCurrentTime: "現在の時間を使用",
+    saveAs: "名前を付けて保存…",
+    globalShortcutsUnavailable: "一部のグローバルショートカットを設定できませんでした。設定ファイルの globalShortcuts を変更してください:",
     cancel: "キャンセル",
     close: "閉じる",
     mute: "ミュート",
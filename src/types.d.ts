@@ -9,7 +9,6 @@ declare global {
         "load-playlist": Mp.LoadPlaylistEvent;
         "add-to-playlist": string[];
         "load-file": Mp.FileLoadEvent;
-        "change-playlist": Mp.ChangePlaylistRequest;
         "toggle-play": Mp.Event;
         "toggle-fullscreen": Mp.Event;
         restart: Mp.Event;
@@ -17,25 +16,78 @@ declare global {
         "release-file-result": Mp.ReleaseFileResult;
         "toggle-convert": Mp.Event;
         "open-convert": Mp.MediaFile;
+        "settings-changed": Mp.Settings;
     };
 
     namespace Mp {
         type Lang = "en" | "ja";
         type Theme = "dark" | "light";
-        type ConvertType = "Video" | "Audio";
+        type SilenceMode = "Off" | "Skip" | "SpeedUp";
+        type LevellingMode = "Off" | "Track" | "Album";
+        type RepeatMode = "Off" | "One" | "All";
+        type ConvertType = "Video" | "Audio" | "Extract";
+        type ExtractFormat = "Copy" | "Mp3" | "Aac" | "Opus" | "Flac" | "Wav";
+        type ExtractSplit = "None" | "Chapters" | "Cue";
         type ThumbButtonType = "Play" | "Pause" | "Previous" | "Next";
         type PlaybackSpeed = 0.25 | 0.5 | 0.75 | 1 | 1.25 | 1.5 | 1.75 | 2;
         type SeekSpeed = 0.03 | 0.05 | 0.1 | 0.5 | 1 | 3 | 5 | 10 | 20;
         type SortOrder = "NameAsc" | "NameDesc" | "DateAsc" | "DateDesc";
         type ThumbButtonId = "Play" | "Pause" | "Previous" | "Next";
+        type PlaybackStatus = "Playing" | "Paused" | "Stopped";
+        type MediaControl =
+            | "Play"
+            | "Pause"
+            | "PlayPause"
+            | "Stop"
+            | "Next"
+            | "Previous"
+            | { Seek: { offset: number } }
+            | { SetPosition: { position: number } }
+            | { Volume: { volume: number } }
+            | { Rate: { rate: number } };
+        type Keymap = { [renderer in RendererName]?: { [id: string]: string } };
+        type GlobalShortcutAction =
+            | "PlayPause"
+            | "Next"
+            | "Previous"
+            | "SeekForward"
+            | "SeekBackward"
+            | "VolumeUp"
+            | "VolumeDown"
+            | "TogglePlaylist";
 
         type PlayerContextMenuSubTypeMap = {
-            PlaybackSpeed: Mp.PlaybackSpeed;
-            SeekSpeed: Mp.SeekSpeed;
+            PlaybackSpeed: number;
+            SeekSpeed: number;
+            CustomPlaybackSpeed: null;
+            CustomSeekSpeed: null;
             TogglePlaylistWindow: null;
             FitToWindow: null;
             ToggleFullscreen: null;
             Theme: Mp.Theme;
+            Lang: "system" | Mp.Lang;
+            Subtitles: string;
+            DelaySubtitles: null;
+            AdvanceSubtitles: null;
+            ResetSubtitleOffset: null;
+            SyncSubtitles: null;
+            ExportSubtitles: null;
+            Chapters: number;
+            NextChapter: null;
+            PreviousChapter: null;
+            Bookmarks: null;
+            AddBookmark: null;
+            EditBookmarks: null;
+            ImportBookmarks: null;
+            ExportBookmarks: null;
+            DetectScenes: null;
+            AbLoop: null;
+            SetLoopStart: null;
+            SetLoopEnd: null;
+            ClearLoop: null;
+            ExportLoop: null;
+            Silence: Mp.SilenceMode;
+            Levelling: Mp.LevellingMode;
             Capture: null;
             PictureInPicture: null;
             ViewSettingsJson: null;
@@ -49,12 +101,19 @@ declare global {
             CopyFullpath: null;
             Reveal: null;
             Metadata: null;
+            EditTags: null;
             Convert: null;
             Sort: Mp.SortOrder;
             Rename: null;
+            RenameFromTags: null;
             Move: null;
+            Copy: null;
+            Undo: null;
+            Redo: null;
             GroupBy: null;
             PasteFilePath: null;
+            PlayNext: null;
+            Repeat: Mp.RepeatMode;
         };
 
         type VideoFrameSize = "SizeNone" | "360p" | "480p" | "720p" | "1080p";
@@ -104,36 +163,43 @@ declare global {
         };
 
         type Settings = {
+            version: number;
             bounds: Bounds;
             playlistBounds: Bounds;
             theme: Mp.Theme;
             isMaximized: boolean;
             playlistVisible: boolean;
             sort: Mp.SortType;
+            playlist: {
+                repeat: Mp.RepeatMode;
+                shuffle: boolean;
+            };
             video: {
                 fitToWindow: boolean;
                 playbackSpeed: number;
                 seekSpeed: number;
+                playbackSpeedPresets: number[];
+                seekSpeedPresets: number[];
             };
             audio: {
                 volume: number;
                 ampLevel: number;
                 mute: boolean;
+                levelling: LevellingMode;
+            };
+            silence: {
+                mode: SilenceMode;
+                speed: number;
+                noise: number;
+                minDuration: number;
             };
             defaultPath: string;
             locale: {
                 mode: "system" | Mp.Lang;
                 lang: Mp.Lang;
             };
-        };
-
-        type TauriSettings = {
-            theme: Mp.Theme;
-            fitToWindow: boolean;
-            playbackSpeed: number;
-            seekSpeed: number;
-            groupBy: boolean;
-            order: Mp.SortOrder;
+            globalShortcuts: { [key in Mp.GlobalShortcutAction]?: string };
+            keymap: Mp.Keymap;
         };
 
         type MediaFile = {
@@ -195,6 +261,15 @@ declare global {
             Volume: MediaVolume;
         };
 
+        type StreamSelector = { Index: number } | { Language: string } | "Exclude";
+
+        type StreamSelection = {
+            video?: StreamSelector;
+            audio?: StreamSelector;
+            subtitle?: StreamSelector;
+            burnSubtitle: boolean;
+        };
+
         type ConvertOptions = {
             format: string;
             frameSize: VideoFrameSize;
@@ -202,6 +277,8 @@ declare global {
             rotation: VideoRotation;
             audioVolume: string;
             maxAudioVolume: boolean;
+            streams: StreamSelection;
+            removeSilence: boolean;
         };
 
         type LoadPlaylistEvent = {
@@ -222,10 +299,6 @@ declare global {
             currentIndex: number;
         };
 
-        type ChangePlaylistRequest = {
-            index: number;
-        };
-
         type ChangePlayStatusRequest = {
             status: PlayStatus;
         };
@@ -294,6 +367,7 @@ declare global {
             sort: string;
             playbackSpeed: string;
             seekSpeed: string;
+            customSpeed: string;
             fitToWindow: string;
             playlist: string;
             fullscreen: string;
@@ -330,9 +404,60 @@ declare global {
             frameSize: string;
             videoRotation: string;
             audioBitrate: string;
+            videoStream: string;
+            audioStream: string;
+            subtitleStream: string;
+            burnSubtitle: string;
+            extractAudio: string;
+            split: string;
+            splitNone: string;
+            splitChapters: string;
+            splitCue: string;
+            selectCueFile: string;
+            selectOutputDir: string;
+            keepBackup: string;
+            save: string;
+            sceneThreshold: string;
+            detect: string;
+            noScenes: string;
+            setLoopPointsFirst: string;
+            saveAsBookmarks: string;
+            writeChapters: string;
+            removeSilence: string;
+            streamDefault: string;
+            streamNone: string;
             volume: string;
             maximizeVolue: string;
             start: string;
+            renameTemplate: string;
+            preview: string;
+            collisions: string;
+            tagRenameReady: string;
+            tagRenameUnchanged: string;
+            tagRenameMissingTags: string;
+            tagRenameExists: string;
+            tagRenameDuplicate: string;
+            tagRenameProbeFailed: string;
+            transferring: string;
+            fileExists: string;
+            skip: string;
+            overwrite: string;
+            keepBoth: string;
+            applyToAll: string;
+            speedPresets: string;
+            addPreset: string;
+            apply: string;
+            bookmarks: string;
+            bookmarkLabel: string;
+            bookmarkColor: string;
+            bookmarkNote: string;
+            noBookmarks: string;
+            syncSubtitles: string;
+            syncPoint: string;
+            subtitleCue: string;
+            useCurrentTime: string;
+            saveAs: string;
+            globalShortcutsUnavailable: string;
             cancel: string;
             close: string;
             mute: string;
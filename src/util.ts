@@ -77,22 +77,6 @@ class Util {
         };
     }
 
-    shuffle(targets: any[]) {
-        const result = [];
-        let size = 0;
-        let randomIndex = 0;
-
-        while (targets.length > 0) {
-            size = targets.length;
-            randomIndex = Math.floor(Math.random() * size);
-
-            result.push(targets[randomIndex]);
-            targets.splice(randomIndex, 1);
-        }
-
-        return result;
-    }
-
     private localCompareName(a: Mp.MediaFile, b: Mp.MediaFile) {
         return a.name.replace(path.extname(a.name), "").localeCompare(b.name.replace(path.extname(a.name), ""));
     }
@@ -196,19 +180,29 @@ class Util {
         };
     }
 
+    private async resolveStreams(sourcePath: string, convertType: Mp.ConvertType, options: Mp.ConvertOptions) {
+        try {
+            return await this.ipc.invoke("resolve_convert_streams", { filePath: sourcePath, convertType, format: options.format, selection: options.streams, removeSilence: options.removeSilence });
+        } catch (ex: any) {
+            throw new Error(String(ex));
+        }
+    }
+
+    private findStream(metadata: Mp.Metadata, index: number | null) {
+        return index === null ? undefined : metadata.streams?.find((stream) => stream.index === index);
+    }
+
     async convertAudio(sourcePath: string, destPath: string, options: Mp.ConvertOptions) {
         if (this.child) throw new Error("Process busy");
 
+        const streams = await this.resolveStreams(sourcePath, "Audio", options);
         const metadata = await this.getMediaMetadata(sourcePath);
 
         this.convertDestFile = destPath;
 
-        if (!metadata.streams[1].bit_rate) {
-            metadata.streams[1].bit_rate = "0";
-        }
-
-        const audioBitrate = options.audioBitrate !== "BitrateNone" ? parseInt(options.audioBitrate) : Math.ceil(parseInt(metadata.streams[1].bit_rate) / 1000);
-        let audioVolume = options.audioVolume !== "1" ? `volume=${options.audioVolume}dB` : "";
+        const audioStream = this.findStream(metadata, streams.audio);
+        const audioBitrate = options.audioBitrate !== "BitrateNone" ? parseInt(options.audioBitrate) : Math.ceil(parseInt(audioStream?.bit_rate ?? "0") / 1000);
+        let audioVolume = options.audioVolume !== "1" ? `volume=${options.audioVolume}` : "";
 
         if (options.maxAudioVolume) {
             const maxVolumeText = metadata.Volume.max_volume;
@@ -216,14 +210,15 @@ class Util {
             if (maxVolume >= 0) {
                 throw new Error("No max_volume");
             }
-            audioVolume = `volume=${maxVolume * -1}dBdb`;
+            audioVolume = `volume=${maxVolume * -1}dB`;
         }
 
-        const args = ["-i", sourcePath, "-y", "-b:a", String(audioBitrate)];
+        const args = ["-i", sourcePath, "-y", ...streams.args, "-b:a", String(audioBitrate)];
 
-        if (audioVolume) {
+        const audioFilters = [streams.audioFilter, audioVolume].filter(Boolean);
+        if (audioFilters.length) {
             args.push("-filter:a");
-            args.push(audioVolume);
+            args.push(audioFilters.join(","));
         }
 
         args.push("-f");
@@ -244,18 +239,17 @@ class Util {
     async convertVideo(sourcePath: string, destPath: string, options: Mp.ConvertOptions) {
         if (this.child) throw new Error("Process busy");
 
+        const streams = await this.resolveStreams(sourcePath, "Video", options);
         const metadata = await this.getMediaMetadata(sourcePath);
 
         this.convertDestFile = destPath;
 
-        const size = Resolutions[options.frameSize] ? Resolutions[options.frameSize] : await this.getSize(metadata);
+        const videoStream = this.findStream(metadata, streams.video);
+        const size = Resolutions[options.frameSize] ? Resolutions[options.frameSize] : await this.getSize(videoStream);
         const rotate = options.rotation != "RotationNone";
 
-        if (!metadata.streams[1].bit_rate) {
-            metadata.streams[1].bit_rate = "0";
-        }
-
-        const audioBitrate = options.audioBitrate !== "BitrateNone" ? parseInt(options.audioBitrate) : Math.ceil(parseInt(metadata.streams[1].bit_rate) / 1000);
+        const audioStream = this.findStream(metadata, streams.audio);
+        const audioBitrate = options.audioBitrate !== "BitrateNone" ? parseInt(options.audioBitrate) : Math.ceil(parseInt(audioStream?.bit_rate ?? "0") / 1000);
 
         let audioVolume = options.audioVolume !== "1" ? `volume=${options.audioVolume}` : "";
 
@@ -268,23 +262,27 @@ class Util {
             audioVolume = `volume=${maxVolume * -1}dB`;
         }
 
-        const args = ["-i", sourcePath, "-y"];
+        const args = ["-i", sourcePath, "-y", ...streams.args];
 
-        if (audioBitrate > 0) {
+        if (streams.audio !== null && audioBitrate > 0) {
             args.push("-b:a"), args.push(String(audioBitrate));
         }
 
-        if (audioVolume) {
+        const audioFilters = [streams.audioFilter, audioVolume].filter(Boolean);
+        if (streams.audio !== null && audioFilters.length) {
             args.push("-filter:a");
-            args.push(`volume=${audioVolume}dB`);
+            args.push(audioFilters.join(","));
         }
 
-        args.push("-filter:v");
+        const videoFilters = [`scale=${size}`];
         if (rotate) {
-            args.push(`scale=${size},transpose=${Rotations[options.rotation]}`);
-        } else {
-            args.push(`scale=${size}`);
+            videoFilters.push(`transpose=${Rotations[options.rotation]}`);
+        }
+        if (streams.videoFilter) {
+            videoFilters.push(streams.videoFilter);
         }
+        args.push("-filter:v");
+        args.push(videoFilters.join(","));
 
         args.push("-f");
         args.push(options.format);
@@ -300,14 +298,16 @@ class Util {
         }
     }
 
-    private async getSize(metadata: Mp.Metadata) {
-        const rotation = metadata.streams[0].rotation;
+    private async getSize(stream: Mp.Metadata["streams"][number] | undefined) {
+        if (!stream) throw new Error("No video stream");
+
+        const rotation = stream.rotation;
 
         if (rotation === "-90" || rotation === "90") {
-            return `${metadata.streams[0].height}x${metadata.streams[0].width}`;
+            return `${stream.height}x${stream.width}`;
         }
 
-        return `${metadata.streams[0].width}x${metadata.streams[0].height}`;
+        return `${stream.width}x${stream.height}`;
     }
 
     private async finishConvert() {